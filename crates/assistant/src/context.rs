@@ -1707,6 +1707,7 @@ impl Context {
             messages: messages.collect(),
             stop: vec![],
             temperature: 1.0,
+            response_format: None,
         }
     }
 
@@ -1968,6 +1969,7 @@ impl Context {
                 messages: messages.collect(),
                 stop: vec![],
                 temperature: 1.0,
+                response_format: None,
             };
 
             self.pending_summary = cx.spawn(|this, mut cx| {
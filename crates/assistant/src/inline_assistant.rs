@@ -2222,6 +2222,7 @@ impl Codegen {
             messages,
             stop: vec!["|END|>".to_string()],
             temperature,
+            response_format: None,
         }
     }
 
@@ -779,6 +779,7 @@ impl PromptLibrary {
                                     }],
                                     stop: Vec::new(),
                                     temperature: 1.,
+                                    response_format: None,
                                 },
                                 cx,
                             )
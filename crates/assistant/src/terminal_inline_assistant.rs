@@ -272,6 +272,7 @@ impl TerminalInlineAssistant {
             messages,
             stop: Vec::new(),
             temperature: 1.0,
+            response_format: None,
         })
     }
 
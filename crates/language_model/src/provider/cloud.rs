@@ -271,6 +271,10 @@ impl LanguageModel for CloudLanguageModel {
         request: LanguageModelRequest,
         _: &AsyncAppContext,
     ) -> BoxFuture<'static, Result<BoxStream<'static, Result<String>>>> {
+        if let Err(error) = request.validate() {
+            return futures::future::ready(Err(error)).boxed();
+        }
+
         match &self.model {
             CloudModel::Anthropic(model) => {
                 let client = self.client.clone();
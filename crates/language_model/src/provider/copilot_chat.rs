@@ -189,6 +189,10 @@ impl LanguageModel for CopilotChatLanguageModel {
         request: LanguageModelRequest,
         cx: &AsyncAppContext,
     ) -> BoxFuture<'static, Result<BoxStream<'static, Result<String>>>> {
+        if let Err(error) = request.validate() {
+            return futures::future::ready(Err(error)).boxed();
+        }
+
         if let Some(message) = request.messages.last() {
             if message.content.trim().is_empty() {
                 const EMPTY_PROMPT_MSG: &str =
@@ -256,19 +260,28 @@ impl LanguageModel for CopilotChatLanguageModel {
 
 impl CopilotChatLanguageModel {
     pub fn to_copilot_chat_request(&self, request: LanguageModelRequest) -> CopilotChatRequest {
+        // Copilot Chat has no native response format, so fall back to prompting.
+        let response_format_message = request
+            .response_format
+            .as_ref()
+            .and_then(|format| format.prompt_instructions())
+            .map(|instructions| ChatMessage {
+                role: CopilotChatRole::System,
+                content: instructions,
+            });
+
         CopilotChatRequest::new(
             self.model.clone(),
-            request
-                .messages
+            response_format_message
                 .into_iter()
-                .map(|msg| ChatMessage {
+                .chain(request.messages.into_iter().map(|msg| ChatMessage {
                     role: match msg.role {
                         Role::User => CopilotChatRole::User,
                         Role::Assistant => CopilotChatRole::Assistant,
                         Role::System => CopilotChatRole::System,
                     },
                     content: msg.content,
-                })
+                }))
                 .collect(),
         )
     }
@@ -255,6 +255,10 @@ impl LanguageModel for GoogleLanguageModel {
         request: LanguageModelRequest,
         cx: &AsyncAppContext,
     ) -> BoxFuture<'static, Result<futures::stream::BoxStream<'static, Result<String>>>> {
+        if let Err(error) = request.validate() {
+            return futures::future::ready(Err(error)).boxed();
+        }
+
         let request = request.into_google(self.model.id().to_string());
 
         let http_client = self.http_client.clone();
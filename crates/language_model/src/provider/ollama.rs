@@ -167,12 +167,25 @@ pub struct OllamaLanguageModel {
 
 impl OllamaLanguageModel {
     fn to_ollama_request(&self, request: LanguageModelRequest) -> ChatRequest {
+        let format = request
+            .response_format
+            .as_ref()
+            .filter(|format| format.is_json())
+            .map(|_| "json".to_string());
+        // Ollama only constrains the response to JSON, so the schema is given in the prompt.
+        let response_format_message = request
+            .response_format
+            .as_ref()
+            .and_then(|format| format.prompt_instructions())
+            .map(|instructions| ChatMessage::System {
+                content: instructions,
+            });
+
         ChatRequest {
             model: self.model.name.clone(),
-            messages: request
-                .messages
+            messages: response_format_message
                 .into_iter()
-                .map(|msg| match msg.role {
+                .chain(request.messages.into_iter().map(|msg| match msg.role {
                     Role::User => ChatMessage::User {
                         content: msg.content,
                     },
@@ -182,7 +195,7 @@ impl OllamaLanguageModel {
                     Role::System => ChatMessage::System {
                         content: msg.content,
                     },
-                })
+                }))
                 .collect(),
             keep_alive: self.model.keep_alive.clone().unwrap_or_default(),
             stream: true,
@@ -192,6 +205,7 @@ impl OllamaLanguageModel {
                 temperature: Some(request.temperature),
                 ..Default::default()
             }),
+            format,
         }
     }
 }
@@ -243,6 +257,10 @@ impl LanguageModel for OllamaLanguageModel {
         request: LanguageModelRequest,
         cx: &AsyncAppContext,
     ) -> BoxFuture<'static, Result<BoxStream<'static, Result<String>>>> {
+        if let Err(error) = request.validate() {
+            return futures::future::ready(Err(error)).boxed();
+        }
+
         let request = self.to_ollama_request(request);
 
         let http_client = self.http_client.clone();
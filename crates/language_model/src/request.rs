@@ -1,4 +1,5 @@
 use crate::role::Role;
+use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
@@ -7,21 +8,233 @@ pub struct LanguageModelRequestMessage {
     pub content: String,
 }
 
+/// The shape the model is asked to respond in.
+///
+/// This is best-effort: OpenAI enforces it server-side, and Google AI enforces the
+/// subset of JSON schema it supports. Ollama only enforces that the response is JSON
+/// and receives the schema as prompting instructions, like the remaining backends.
+/// Either way the completion is still streamed as text chunks, and callers are
+/// responsible for parsing the accumulated JSON.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseFormat {
+    Text,
+    JsonObject,
+    JsonSchema(serde_json::Value),
+}
+
+impl ResponseFormat {
+    /// Checks that a JSON schema looks well-formed before it is sent to a provider.
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            ResponseFormat::Text | ResponseFormat::JsonObject => Ok(()),
+            ResponseFormat::JsonSchema(schema) => validate_schema(schema, "#"),
+        }
+    }
+
+    pub fn json_schema(&self) -> Option<&serde_json::Value> {
+        match self {
+            ResponseFormat::JsonSchema(schema) => Some(schema),
+            ResponseFormat::Text | ResponseFormat::JsonObject => None,
+        }
+    }
+
+    pub fn is_json(&self) -> bool {
+        !matches!(self, ResponseFormat::Text)
+    }
+
+    pub fn prompt_instructions(&self) -> Option<String> {
+        match self {
+            ResponseFormat::Text => None,
+            ResponseFormat::JsonObject => {
+                Some("Respond only with a single valid JSON object and no other text.".into())
+            }
+            ResponseFormat::JsonSchema(schema) => Some(format!(
+                "Respond only with a single valid JSON value conforming to the following JSON schema, and no other text:\n{schema}"
+            )),
+        }
+    }
+}
+
+fn validate_schema(schema: &serde_json::Value, path: &str) -> Result<()> {
+    let object = match schema {
+        // `true` and `false` are valid schemas that accept everything and nothing.
+        serde_json::Value::Bool(_) => return Ok(()),
+        serde_json::Value::Object(object) => object,
+        _ => return Err(anyhow!("schema at {path} must be an object or a boolean")),
+    };
+
+    if let Some(ty) = object.get("type") {
+        let is_valid = match ty {
+            serde_json::Value::String(_) => true,
+            serde_json::Value::Array(types) => types.iter().all(|ty| ty.is_string()),
+            _ => false,
+        };
+        if !is_valid {
+            return Err(anyhow!(
+                "\"type\" at {path} must be a string or an array of strings"
+            ));
+        }
+    }
+
+    if let Some(required) = object.get("required") {
+        let is_valid = required
+            .as_array()
+            .map_or(false, |required| required.iter().all(|key| key.is_string()));
+        if !is_valid {
+            return Err(anyhow!(
+                "\"required\" at {path} must be an array of strings"
+            ));
+        }
+    }
+
+    for keyword in ["properties", "definitions", "$defs"] {
+        if let Some(subschemas) = object.get(keyword) {
+            let subschemas = subschemas
+                .as_object()
+                .ok_or_else(|| anyhow!("\"{keyword}\" at {path} must be an object"))?;
+            for (name, subschema) in subschemas {
+                validate_schema(subschema, &format!("{path}/{keyword}/{name}"))?;
+            }
+        }
+    }
+
+    for keyword in ["items", "additionalProperties", "not"] {
+        if let Some(subschema) = object.get(keyword) {
+            validate_schema(subschema, &format!("{path}/{keyword}"))?;
+        }
+    }
+
+    for keyword in ["anyOf", "allOf", "oneOf"] {
+        if let Some(subschemas) = object.get(keyword) {
+            let subschemas = subschemas
+                .as_array()
+                .ok_or_else(|| anyhow!("\"{keyword}\" at {path} must be an array"))?;
+            for (ix, subschema) in subschemas.iter().enumerate() {
+                validate_schema(subschema, &format!("{path}/{keyword}/{ix}"))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Converts a JSON schema to the OpenAPI subset Google AI accepts as a response schema.
+/// Local `$ref`s are inlined, `["<type>", "null"]` becomes a nullable type, and keywords
+/// it doesn't support, like `additionalProperties` or `$schema`, are dropped. Returns
+/// `None` for schemas it can't represent, like other type unions; such properties and
+/// items are left out of their parent.
+fn google_ai_schema(
+    schema: &serde_json::Value,
+    root: &serde_json::Value,
+    depth: usize,
+) -> Option<serde_json::Value> {
+    // Recursive schemas are cut off rather than inlined forever.
+    const MAX_DEPTH: usize = 16;
+    if depth > MAX_DEPTH {
+        return None;
+    }
+    let object = schema.as_object()?;
+    if let Some(reference) = object.get("$ref") {
+        let target = root.pointer(reference.as_str()?.strip_prefix('#')?)?;
+        return google_ai_schema(target, root, depth + 1);
+    }
+
+    let mut nullable = object
+        .get("nullable")
+        .and_then(|nullable| nullable.as_bool());
+    let ty = match object.get("type") {
+        Some(serde_json::Value::String(ty)) => ty.as_str(),
+        Some(serde_json::Value::Array(types)) => {
+            let mut types = types.iter().filter_map(|ty| ty.as_str());
+            let types = (types.next(), types.next(), types.next());
+            match types {
+                (Some(ty), None, None) => ty,
+                (Some("null"), Some(ty), None) | (Some(ty), Some("null"), None) => {
+                    nullable = Some(true);
+                    ty
+                }
+                _ => return None,
+            }
+        }
+        Some(_) => return None,
+        None if object.contains_key("properties") => "object",
+        None if object.contains_key("items") => "array",
+        None if object.contains_key("enum") => "string",
+        None => return None,
+    };
+
+    let mut converted = serde_json::Map::new();
+    converted.insert("type".into(), ty.to_uppercase().into());
+    if let Some(nullable) = nullable {
+        converted.insert("nullable".into(), nullable.into());
+    }
+    for keyword in ["format", "description", "enum", "minItems", "maxItems"] {
+        if let Some(value) = object.get(keyword) {
+            converted.insert(keyword.into(), value.clone());
+        }
+    }
+    if let Some(properties) = object.get("properties").and_then(|value| value.as_object()) {
+        let properties = properties
+            .iter()
+            .filter_map(|(name, property)| {
+                Some((name.clone(), google_ai_schema(property, root, depth + 1)?))
+            })
+            .collect::<serde_json::Map<_, _>>();
+        if let Some(required) = object.get("required").and_then(|value| value.as_array()) {
+            let required = required
+                .iter()
+                .filter(|name| {
+                    name.as_str()
+                        .map_or(false, |name| properties.contains_key(name))
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            converted.insert("required".into(), required.into());
+        }
+        converted.insert("properties".into(), properties.into());
+    }
+    if let Some(items) = object.get("items") {
+        if let Some(items) = google_ai_schema(items, root, depth + 1) {
+            converted.insert("items".into(), items);
+        }
+    }
+    Some(converted.into())
+}
+
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct LanguageModelRequest {
     pub messages: Vec<LanguageModelRequestMessage>,
     pub stop: Vec<String>,
     pub temperature: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
 }
 
 impl LanguageModelRequest {
+    pub fn validate(&self) -> Result<()> {
+        if let Some(response_format) = &self.response_format {
+            response_format.validate()?;
+        }
+        Ok(())
+    }
+
     pub fn into_open_ai(self, model: String) -> open_ai::Request {
+        // OpenAI rejects `json_object` unless the messages ask for JSON.
+        let response_format_message = self
+            .response_format
+            .as_ref()
+            .filter(|format| **format == ResponseFormat::JsonObject)
+            .and_then(|format| format.prompt_instructions())
+            .map(|instructions| open_ai::RequestMessage::System {
+                content: instructions,
+            });
+
         open_ai::Request {
             model,
-            messages: self
-                .messages
+            messages: response_format_message
                 .into_iter()
-                .map(|msg| match msg.role {
+                .chain(self.messages.into_iter().map(|msg| match msg.role {
                     Role::User => open_ai::RequestMessage::User {
                         content: msg.content,
                     },
@@ -32,23 +245,56 @@ impl LanguageModelRequest {
                     Role::System => open_ai::RequestMessage::System {
                         content: msg.content,
                     },
-                })
+                }))
                 .collect(),
             stream: true,
             stop: self.stop,
             temperature: self.temperature,
             tools: Vec::new(),
             tool_choice: None,
+            response_format: self.response_format.map(|format| match format {
+                ResponseFormat::Text => open_ai::ResponseFormat::Text,
+                ResponseFormat::JsonObject => open_ai::ResponseFormat::JsonObject,
+                ResponseFormat::JsonSchema(schema) => open_ai::ResponseFormat::JsonSchema {
+                    json_schema: open_ai::JsonSchemaDefinition {
+                        name: "response".into(),
+                        schema,
+                        strict: None,
+                    },
+                },
+            }),
         }
     }
 
     pub fn into_google(self, model: String) -> google_ai::GenerateContentRequest {
+        let response_mime_type = self
+            .response_format
+            .as_ref()
+            .filter(|format| format.is_json())
+            .map(|_| "application/json".to_string());
+        let schema = self
+            .response_format
+            .as_ref()
+            .and_then(|format| format.json_schema());
+        let response_schema = schema.and_then(|schema| google_ai_schema(schema, schema, 0));
+        // Schemas Google AI can't represent are only given as prompting instructions.
+        let response_format_message = self
+            .response_format
+            .as_ref()
+            .filter(|_| schema.is_some() && response_schema.is_none())
+            .and_then(|format| format.prompt_instructions())
+            .map(|instructions| google_ai::Content {
+                parts: vec![google_ai::Part::TextPart(google_ai::TextPart {
+                    text: instructions,
+                })],
+                role: google_ai::Role::User,
+            });
+
         google_ai::GenerateContentRequest {
             model,
-            contents: self
-                .messages
+            contents: response_format_message
                 .into_iter()
-                .map(|msg| google_ai::Content {
+                .chain(self.messages.into_iter().map(|msg| google_ai::Content {
                     parts: vec![google_ai::Part::TextPart(google_ai::TextPart {
                         text: msg.content,
                     })],
@@ -57,7 +303,7 @@ impl LanguageModelRequest {
                         Role::Assistant => google_ai::Role::Model,
                         Role::System => google_ai::Role::User, // Google AI doesn't have a system role
                     },
-                })
+                }))
                 .collect(),
             generation_config: Some(google_ai::GenerationConfig {
                 candidate_count: Some(1),
@@ -66,6 +312,8 @@ impl LanguageModelRequest {
                 temperature: Some(self.temperature as f64),
                 top_p: None,
                 top_k: None,
+                response_mime_type,
+                response_schema,
             }),
             safety_settings: None,
         }
@@ -101,6 +349,18 @@ impl LanguageModelRequest {
             }
         }
 
+        // Anthropic has no native response format, so fall back to prompting.
+        if let Some(instructions) = self
+            .response_format
+            .as_ref()
+            .and_then(|format| format.prompt_instructions())
+        {
+            if !system_message.is_empty() {
+                system_message.push_str("\n\n");
+            }
+            system_message.push_str(&instructions);
+        }
+
         anthropic::Request {
             model,
             messages: new_messages
@@ -136,3 +396,114 @@ pub struct LanguageModelResponseMessage {
     pub role: Option<Role>,
     pub content: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_response_format_validation() {
+        assert!(ResponseFormat::Text.validate().is_ok());
+        assert!(ResponseFormat::JsonObject.validate().is_ok());
+        assert!(ResponseFormat::JsonSchema(json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "tags": { "type": "array", "items": { "type": "string" } },
+            },
+            "required": ["name"],
+        }))
+        .validate()
+        .is_ok());
+
+        assert!(ResponseFormat::JsonSchema(json!("object"))
+            .validate()
+            .is_err());
+        assert!(ResponseFormat::JsonSchema(json!({ "type": 1 }))
+            .validate()
+            .is_err());
+        assert!(ResponseFormat::JsonSchema(json!({ "required": "name" }))
+            .validate()
+            .is_err());
+        assert!(ResponseFormat::JsonSchema(json!({
+            "type": "object",
+            "properties": { "name": { "type": ["string", 2] } },
+        }))
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_open_ai_json_object_asks_for_json() {
+        let request = LanguageModelRequest {
+            messages: vec![LanguageModelRequestMessage {
+                role: Role::User,
+                content: "List three colors".into(),
+            }],
+            response_format: Some(ResponseFormat::JsonObject),
+            ..Default::default()
+        };
+        let request = request.into_open_ai("gpt-4o".into());
+        assert_eq!(request.messages.len(), 2);
+        match &request.messages[0] {
+            open_ai::RequestMessage::System { content } => assert!(content.contains("JSON")),
+            message => panic!("expected a system message, got {message:?}"),
+        }
+    }
+
+    #[test]
+    fn test_google_ai_schema() {
+        let schema = json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "type": "object",
+            "additionalProperties": false,
+            "properties": {
+                "name": { "type": "string", "description": "The name" },
+                "nickname": { "type": ["string", "null"] },
+                "id": { "type": ["string", "integer"] },
+                "tags": { "type": "array", "items": { "$ref": "#/$defs/tag" } },
+            },
+            "required": ["name", "id"],
+            "$defs": {
+                "tag": { "enum": ["a", "b"] },
+            },
+        });
+        assert_eq!(
+            google_ai_schema(&schema, &schema, 0),
+            Some(json!({
+                "type": "OBJECT",
+                "properties": {
+                    "name": { "type": "STRING", "description": "The name" },
+                    "nickname": { "type": "STRING", "nullable": true },
+                    "tags": {
+                        "type": "ARRAY",
+                        "items": { "type": "STRING", "enum": ["a", "b"] },
+                    },
+                },
+                "required": ["name"],
+            }))
+        );
+
+        let recursive = json!({
+            "type": "object",
+            "properties": { "child": { "$ref": "#" } },
+        });
+        assert!(google_ai_schema(&recursive, &recursive, 0).is_some());
+        assert_eq!(google_ai_schema(&json!(true), &json!(true), 0), None);
+
+        // What can't be converted is asked for in the prompt instead
+        let request = LanguageModelRequest {
+            response_format: Some(ResponseFormat::JsonSchema(json!({ "anyOf": [] }))),
+            ..Default::default()
+        };
+        let request = request.into_google("gemini-1.5-pro".into());
+        assert_eq!(request.contents.len(), 1);
+        let generation_config = request.generation_config.unwrap();
+        assert_eq!(generation_config.response_schema, None);
+        assert_eq!(
+            generation_config.response_mime_type.as_deref(),
+            Some("application/json")
+        );
+    }
+}
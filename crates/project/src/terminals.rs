@@ -13,7 +13,7 @@ use std::{
 use task::{Shell, SpawnInTerminal};
 use terminal::{
     terminal_settings::{self, TerminalSettings},
-    TaskState, TaskStatus, Terminal, TerminalBuilder,
+    TaskState, TaskStatus, Terminal, TerminalBuilder, TerminalOptions,
 };
 use util::ResultExt;
 
@@ -207,9 +207,7 @@ impl Project {
             spawn_task,
             shell,
             env,
-            Some(settings.blinking),
-            settings.alternate_scroll,
-            settings.max_scroll_history_lines,
+            TerminalOptions::from(settings),
             window,
             completion_tx,
             cx,
@@ -42,6 +42,12 @@ impl AlacModifiers {
 }
 
 pub fn to_esc_str(keystroke: &Keystroke, mode: &TermMode, alt_is_meta: bool) -> Option<String> {
+    if mode.intersects(TermMode::DISAMBIGUATE_ESC_CODES | TermMode::REPORT_ALL_KEYS_AS_ESC) {
+        if let Some(esc_str) = kitty_esc_str(keystroke, mode, alt_is_meta) {
+            return Some(esc_str);
+        }
+    }
+
     let modifiers = AlacModifiers::new(keystroke);
 
     // Manual Bindings including modifiers
@@ -210,7 +216,7 @@ pub fn to_esc_str(keystroke: &Keystroke, mode: &TermMode, alt_is_meta: bool) ->
             "f2" => Some(format!("\x1b[1;{}Q", modifier_code)),
             "f3" => Some(format!("\x1b[1;{}R", modifier_code)),
             "f4" => Some(format!("\x1b[1;{}S", modifier_code)),
-            "F5" => Some(format!("\x1b[15;{}~", modifier_code)),
+            "f5" => Some(format!("\x1b[15;{}~", modifier_code)),
             "f6" => Some(format!("\x1b[17;{}~", modifier_code)),
             "f7" => Some(format!("\x1b[18;{}~", modifier_code)),
             "f8" => Some(format!("\x1b[19;{}~", modifier_code)),
@@ -253,6 +259,47 @@ pub fn to_esc_str(keystroke: &Keystroke, mode: &TermMode, alt_is_meta: bool) ->
     None
 }
 
+/// Encodes keys as `CSI code;modifiers u` for programs that enabled the kitty keyboard
+/// protocol. Only keys that the legacy encoding can't tell apart (Escape, Ctrl-I from Tab,
+/// ...) are encoded, unless the program asked for all keys to be reported this way.
+/// Everything else falls back to the legacy sequences, which are unambiguous.
+/// Alt with a text key is left to the IME unless alt is meta, so that characters
+/// like å or ñ can still be typed.
+/// from: https://sw.kovidgoyal.net/kitty/keyboard-protocol/
+fn kitty_esc_str(keystroke: &Keystroke, mode: &TermMode, alt_is_meta: bool) -> Option<String> {
+    let code = match keystroke.key.as_ref() {
+        "escape" => 27,
+        "enter" => 13,
+        "tab" => 9,
+        "backspace" => 127,
+        "space" => 32,
+        key => {
+            let mut chars = key.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if c.is_ascii_graphic() => c.to_ascii_lowercase() as u32,
+                _ => return None,
+            }
+        }
+    };
+
+    let modifier_code = modifier_code(keystroke);
+    let is_text = !matches!(code, 27 | 13 | 9 | 127);
+    if is_text && keystroke.modifiers.alt && !alt_is_meta {
+        return None;
+    }
+    // Keys producing text still do so when only shifted
+    let is_ambiguous = code == 27 || (modifier_code > 1 && !(is_text && modifier_code == 2));
+    if !is_ambiguous && !mode.contains(TermMode::REPORT_ALL_KEYS_AS_ESC) {
+        return None;
+    }
+
+    if modifier_code == 1 {
+        Some(format!("\x1b[{}u", code))
+    } else {
+        Some(format!("\x1b[{};{}u", code, modifier_code))
+    }
+}
+
 ///   Code     Modifiers
 /// ---------+---------------------------
 ///    2     | Shift
@@ -262,6 +309,7 @@ pub fn to_esc_str(keystroke: &Keystroke, mode: &TermMode, alt_is_meta: bool) ->
 ///    6     | Shift + Control
 ///    7     | Alt + Control
 ///    8     | Shift + Alt + Control
+///  9 - 16  | Meta + the above
 /// ---------+---------------------------
 /// from: https://invisible-island.net/xterm/ctlseqs/ctlseqs.html#h2-PC-Style-Function-Keys
 fn modifier_code(keystroke: &Keystroke) -> u32 {
@@ -275,6 +323,9 @@ fn modifier_code(keystroke: &Keystroke) -> u32 {
     if keystroke.modifiers.control {
         modifier_code |= 1 << 2;
     }
+    if keystroke.modifiers.platform {
+        modifier_code |= 1 << 3;
+    }
     modifier_code + 1
 }
 
@@ -384,7 +435,8 @@ mod test {
     fn test_ctrl_codes() {
         let letters_lower = 'a'..='z';
         let letters_upper = 'A'..='Z';
-        let mode = TermMode::ANY;
+        // The kitty keyboard protocol reports shift, so the codes differ there
+        let mode = TermMode::ANY.difference(TermMode::KITTY_KEYBOARD_PROTOCOL);
 
         for (lower, upper) in letters_lower.zip(letters_upper) {
             assert_eq!(
@@ -421,7 +473,7 @@ mod test {
         }
 
         let gpui_keys = [
-            "up", "down", "right", "left", "f1", "f2", "f3", "f4", "F5", "f6", "f7", "f8", "f9",
+            "up", "down", "right", "left", "f1", "f2", "f3", "f4", "f5", "f6", "f7", "f8", "f9",
             "f10", "f11", "f12", "f13", "f14", "f15", "f16", "f17", "f18", "f19", "f20", "insert",
             "pageup", "pagedown", "end", "home",
         ];
@@ -439,6 +491,131 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_modified_special_keys() {
+        let keys = [
+            ("up", "1", 'A'),
+            ("down", "1", 'B'),
+            ("right", "1", 'C'),
+            ("left", "1", 'D'),
+            ("home", "1", 'H'),
+            ("end", "1", 'F'),
+            ("pageup", "5", '~'),
+            ("pagedown", "6", '~'),
+            ("f1", "1", 'P'),
+            ("f2", "1", 'Q'),
+            ("f3", "1", 'R'),
+            ("f4", "1", 'S'),
+            ("f5", "15", '~'),
+            ("f6", "17", '~'),
+            ("f7", "18", '~'),
+            ("f8", "19", '~'),
+            ("f9", "20", '~'),
+            ("f10", "21", '~'),
+            ("f11", "23", '~'),
+            ("f12", "24", '~'),
+        ];
+        let modifiers = [
+            ("shift-", 2),
+            ("alt-", 3),
+            ("shift-alt-", 4),
+            ("ctrl-", 5),
+            ("shift-ctrl-", 6),
+            ("alt-ctrl-", 7),
+            ("shift-alt-ctrl-", 8),
+            ("cmd-", 9),
+            ("shift-cmd-", 10),
+            ("alt-cmd-", 11),
+            ("shift-alt-cmd-", 12),
+            ("ctrl-cmd-", 13),
+            ("shift-ctrl-cmd-", 14),
+            ("alt-ctrl-cmd-", 15),
+            ("shift-alt-ctrl-cmd-", 16),
+        ];
+        // Shifted navigation keys scroll the terminal outside of the alt screen
+        let mode = TermMode::ALT_SCREEN;
+
+        for (key, number, terminator) in keys {
+            for (prefix, code) in modifiers {
+                let keystroke = Keystroke::parse(&format!("{}{}", prefix, key)).unwrap();
+                assert_eq!(
+                    to_esc_str(&keystroke, &mode, false),
+                    Some(format!("\x1b[{};{}{}", number, code, terminator)),
+                    "On keystroke: {}{}",
+                    prefix,
+                    key
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_kitty_keyboard_protocol() {
+        let tab = Keystroke::parse("tab").unwrap();
+        let ctrl_i = Keystroke::parse("ctrl-i").unwrap();
+        let escape = Keystroke::parse("escape").unwrap();
+        let shift_enter = Keystroke::parse("shift-enter").unwrap();
+        let alt_a = Keystroke::parse("alt-a").unwrap();
+        let shift_a = Keystroke::parse("shift-A").unwrap();
+        let up = Keystroke::parse("up").unwrap();
+
+        // Legacy encoding can't tell Ctrl-I from Tab
+        let legacy = TermMode::NONE;
+        assert_eq!(to_esc_str(&tab, &legacy, false), Some("\x09".to_string()));
+        assert_eq!(
+            to_esc_str(&ctrl_i, &legacy, false),
+            Some("\x09".to_string())
+        );
+        assert_eq!(
+            to_esc_str(&escape, &legacy, false),
+            Some("\x1b".to_string())
+        );
+        assert_eq!(
+            to_esc_str(&shift_enter, &legacy, false),
+            Some("\x0d".to_string())
+        );
+
+        let disambiguate = TermMode::DISAMBIGUATE_ESC_CODES;
+        assert_eq!(
+            to_esc_str(&tab, &disambiguate, false),
+            Some("\x09".to_string())
+        );
+        assert_eq!(
+            to_esc_str(&ctrl_i, &disambiguate, false),
+            Some("\x1b[105;5u".to_string())
+        );
+        assert_eq!(
+            to_esc_str(&escape, &disambiguate, false),
+            Some("\x1b[27u".to_string())
+        );
+        assert_eq!(
+            to_esc_str(&shift_enter, &disambiguate, false),
+            Some("\x1b[13;2u".to_string())
+        );
+        assert_eq!(
+            to_esc_str(&alt_a, &disambiguate, true),
+            Some("\x1b[97;3u".to_string())
+        );
+        // Without alt as meta, the character composed by the IME is typed instead
+        assert_eq!(to_esc_str(&alt_a, &disambiguate, false), None);
+        assert_eq!(to_esc_str(&shift_a, &disambiguate, false), None);
+        assert_eq!(
+            to_esc_str(&up, &disambiguate, false),
+            Some("\x1b[A".to_string())
+        );
+
+        let all_keys = TermMode::DISAMBIGUATE_ESC_CODES | TermMode::REPORT_ALL_KEYS_AS_ESC;
+        assert_eq!(
+            to_esc_str(&tab, &all_keys, false),
+            Some("\x1b[9u".to_string())
+        );
+        assert_eq!(
+            to_esc_str(&shift_a, &all_keys, false),
+            Some("\x1b[97;2u".to_string())
+        );
+        assert_eq!(to_esc_str(&alt_a, &all_keys, false), None);
+    }
+
     #[test]
     fn test_modifier_code_calc() {
         //   Code     Modifiers
@@ -462,5 +639,6 @@ mod test {
             8,
             modifier_code(&Keystroke::parse("shift-ctrl-alt-A").unwrap())
         );
+        assert_eq!(9, modifier_code(&Keystroke::parse("cmd-A").unwrap()));
     }
 }
@@ -5,10 +5,10 @@ use alacritty_terminal::grid::Dimensions;
 /// Most of the code, and specifically the constants, in this are copied from Alacritty,
 /// with modifications for our circumstances
 use alacritty_terminal::index::{Column as GridCol, Line as GridLine, Point as AlacPoint, Side};
-use alacritty_terminal::term::TermMode;
+use alacritty_terminal::term::{cell::Flags, TermMode};
 use gpui::{px, Modifiers, MouseButton, MouseMoveEvent, Pixels, Point, ScrollWheelEvent};
 
-use crate::TerminalSize;
+use crate::{TerminalContent, TerminalSize};
 
 enum MouseFormat {
     SGR,
@@ -38,6 +38,8 @@ enum AlacMouseButton {
     NoneMove = 35,
     ScrollUp = 64,
     ScrollDown = 65,
+    ScrollLeft = 66,
+    ScrollRight = 67,
     Other = 99,
 }
 
@@ -104,6 +106,31 @@ pub fn scroll_report(
     }
 }
 
+pub fn horizontal_scroll_report(
+    point: AlacPoint,
+    scroll_columns: i32,
+    e: &ScrollWheelEvent,
+    mode: TermMode,
+) -> Option<impl Iterator<Item = Vec<u8>>> {
+    if scroll_columns == 0 || !mode.intersects(TermMode::MOUSE_MODE) {
+        return None;
+    }
+
+    let button = if scroll_columns > 0 {
+        AlacMouseButton::ScrollLeft
+    } else {
+        AlacMouseButton::ScrollRight
+    };
+    mouse_report(
+        point,
+        button,
+        true,
+        e.modifiers,
+        MouseFormat::from_mode(mode),
+    )
+    .map(|report| repeat(report).take(scroll_columns.unsigned_abs() as usize))
+}
+
 pub fn alt_scroll(scroll_lines: i32) -> Vec<u8> {
     let cmd = if scroll_lines > 0 { b'A' } else { b'B' };
 
@@ -195,6 +222,27 @@ pub fn grid_point_and_side(
     )
 }
 
+///Wide characters (CJK, emoji) span two cells, the second being a spacer. Points on the
+///spacer are moved onto the character, on its right side, so that clicks and selections
+///land on the glyph drawn there
+pub fn wide_char_point_and_side(
+    point: AlacPoint,
+    side: Side,
+    content: &TerminalContent,
+) -> (AlacPoint, Side) {
+    let row = point.line.0 + content.display_offset as i32;
+    if row < 0 || point.column.0 == 0 {
+        return (point, side);
+    }
+    let index = row as usize * content.size.columns() + point.column.0;
+    match content.cells.get(index) {
+        Some(cell) if cell.point == point && cell.flags.contains(Flags::WIDE_CHAR_SPACER) => {
+            (AlacPoint::new(point.line, point.column - 1), Side::Right)
+        }
+        _ => (point, side),
+    }
+}
+
 ///Generate the bytes to send to the terminal, from the cell location, a mouse event, and the terminal mode
 fn mouse_report(
     point: AlacPoint,
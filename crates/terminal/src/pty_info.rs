@@ -4,12 +4,17 @@ use std::num::NonZeroU32;
 #[cfg(unix)]
 use std::os::fd::AsRawFd;
 use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
 #[cfg(target_os = "windows")]
 use windows::Win32::{Foundation::HANDLE, System::Threading::GetProcessId};
 
 use sysinfo::{Pid, Process, ProcessRefreshKind, RefreshKind, System, UpdateKind};
 
+/// Reading process info walks /proc or its platform equivalent, which is too
+/// expensive to do on every wakeup under heavy output.
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+
 struct ProcessIdGetter {
     handle: i32,
     fallback_pid: u32,
@@ -31,6 +36,18 @@ impl ProcessIdGetter {
         }
         Some(Pid::from_u32(pid as u32))
     }
+
+    fn send_signal(&self, signal: libc::c_int) -> std::io::Result<()> {
+        let mut pgrp = unsafe { libc::tcgetpgrp(self.handle) };
+        if pgrp < 0 {
+            // The shell leads its own process group
+            pgrp = self.fallback_pid as libc::pid_t;
+        }
+        if unsafe { libc::killpg(pgrp, signal) } < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
 }
 
 #[cfg(windows)]
@@ -76,6 +93,7 @@ pub struct PtyProcessInfo {
     system: System,
     refresh_kind: ProcessRefreshKind,
     pid_getter: ProcessIdGetter,
+    last_refresh: Option<Instant>,
     pub current: Option<ProcessInfo>,
 }
 
@@ -92,10 +110,27 @@ impl PtyProcessInfo {
             system,
             refresh_kind: process_refresh_kind,
             pid_getter: ProcessIdGetter::new(pty),
+            last_refresh: None,
             current: None,
         }
     }
 
+    /// The pid of the process spawned in the PTY, usually the shell.
+    pub fn shell_pid(&self) -> u32 {
+        self.pid_getter.fallback_pid
+    }
+
+    /// The pid of the PTY's foreground process group, the shell itself when it's idle.
+    pub fn foreground_pid(&self) -> Option<u32> {
+        self.pid_getter.pid().map(|pid| pid.as_u32())
+    }
+
+    /// Sends the signal to the PTY's foreground process group.
+    #[cfg(unix)]
+    pub fn send_signal(&self, signal: libc::c_int) -> std::io::Result<()> {
+        self.pid_getter.send_signal(signal)
+    }
+
     fn refresh(&mut self) -> Option<&Process> {
         let pid = self.pid_getter.pid()?;
         if self
@@ -120,12 +155,26 @@ impl PtyProcessInfo {
             cwd,
             argv: process.cmd().to_vec(),
         };
-        self.current = Some(info.clone());
         Some(info)
     }
 
-    /// Updates the cached process info, returns whether the Zed-relevant info has changed
+    /// How long until [`Self::has_changed`] will refresh again, `None` if it would refresh now.
+    pub fn throttled_for(&self) -> Option<Duration> {
+        let elapsed = self.last_refresh?.elapsed();
+        MIN_REFRESH_INTERVAL
+            .checked_sub(elapsed)
+            .filter(|remaining| !remaining.is_zero())
+    }
+
+    /// Updates the cached process info, returns whether the Zed-relevant info has changed.
+    /// Updates are rate limited, so this returns false when called again too soon,
+    /// see [`Self::throttled_for`].
     pub fn has_changed(&mut self) -> bool {
+        if self.throttled_for().is_some() {
+            return false;
+        }
+        self.last_refresh = Some(Instant::now());
+
         let current = self.load();
         let has_changed = match (self.current.as_ref(), current.as_ref()) {
             (None, None) => false,
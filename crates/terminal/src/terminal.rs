@@ -9,16 +9,20 @@ use alacritty_terminal::{
     event::{Event as AlacTermEvent, EventListener, Notify, WindowSize},
     event_loop::{EventLoop, Msg, Notifier},
     grid::{Dimensions, Scroll as AlacScroll},
-    index::{Boundary, Column, Direction as AlacDirection, Line, Point as AlacPoint},
+    index::{Boundary, Column, Direction as AlacDirection, Line, Point as AlacPoint, Side},
     selection::{Selection, SelectionRange, SelectionType},
     sync::FairMutex,
     term::{
-        cell::Cell,
+        cell::{Cell, Flags, Hyperlink},
         search::{Match, RegexIter, RegexSearch},
         Config, RenderableCursor, TermMode,
     },
     tty::{self, setup_env},
-    vte::ansi::{ClearMode, Handler, NamedPrivateMode, PrivateMode, Rgb},
+    vi_mode::{ViModeCursor, ViMotion},
+    vte::ansi::{
+        ClearMode, Color as AnsiColor, CursorShape as AlacCursorShape,
+        CursorStyle as AlacCursorStyle, Handler, NamedColor, NamedPrivateMode, PrivateMode, Rgb,
+    },
     Term,
 };
 use anyhow::{bail, Result};
@@ -29,8 +33,8 @@ use futures::{
 };
 
 use mappings::mouse::{
-    alt_scroll, grid_point, grid_point_and_side, mouse_button_report, mouse_moved_report,
-    scroll_report,
+    alt_scroll, grid_point, grid_point_and_side, horizontal_scroll_report, mouse_button_report,
+    mouse_moved_report, scroll_report, wide_char_point_and_side,
 };
 
 use collections::{HashMap, VecDeque};
@@ -40,16 +44,24 @@ use serde::{Deserialize, Serialize};
 use settings::Settings;
 use smol::channel::{Receiver, Sender};
 use task::{HideStrategy, Shell, TaskId};
-use terminal_settings::{AlternateScroll, TerminalBlink, TerminalSettings};
+use terminal_settings::{
+    AlternateScroll, CursorShape, KeepOpenOnExit, TerminalBell, TerminalBlink, TerminalSettings,
+    TitleSource,
+};
 use theme::{ActiveTheme, Theme};
-use util::truncate_and_trailoff;
+use util::{paths::PathWithPosition, truncate_and_trailoff};
 
 use std::{
+    borrow::Cow,
     cmp::{self, min},
     fmt::Display,
+    iter,
     ops::{Deref, Index, RangeInclusive},
     path::PathBuf,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     time::Duration,
 };
 use thiserror::Error;
@@ -67,6 +79,8 @@ actions!(
     [
         Clear,
         Copy,
+        CopyAsAnsi,
+        CopyAsHtml,
         Paste,
         ShowCharacterPalette,
         SearchTest,
@@ -79,29 +93,56 @@ actions!(
     ]
 );
 
-///Scrolling is unbearably sluggish by default. Alacritty supports a configurable
-///Scroll multiplier that is set to 3 by default. This will be removed when I
-///Implement scroll bars.
+///Scrolling is unbearably sluggish without a multiplier. This is used unless the user
+///overrides it with the `scroll_multiplier` setting.
 #[cfg(target_os = "macos")]
-const SCROLL_MULTIPLIER: f32 = 4.;
+const DEFAULT_SCROLL_MULTIPLIER: f32 = 4.;
 #[cfg(not(target_os = "macos"))]
-const SCROLL_MULTIPLIER: f32 = 1.;
+const DEFAULT_SCROLL_MULTIPLIER: f32 = 1.;
+///Smaller multipliers, including 0 and negative ones, would stop or invert scrolling
+const MIN_SCROLL_MULTIPLIER: f32 = 0.01;
 const MAX_SEARCH_LINES: usize = 100;
 const DEBUG_TERMINAL_WIDTH: Pixels = px(500.);
 const DEBUG_TERMINAL_HEIGHT: Pixels = px(30.);
 const DEBUG_CELL_WIDTH: Pixels = px(5.);
 const DEBUG_LINE_HEIGHT: Pixels = px(5.);
+///Shorter blink intervals would redraw the terminal constantly
+const MIN_BLINK_INTERVAL_MS: u64 = 100;
+///How long the terminal background flashes for a visual bell
+const BELL_FLASH_DURATION: Duration = Duration::from_millis(150);
+///How long to wait for further resizes before resizing the grid and the PTY
+const RESIZE_DEBOUNCE: Duration = Duration::from_millis(16);
+///Events arriving after the first one are batched so that floods of output cost one
+///model update per batch rather than one per event. A batch ends when it holds this
+///many events, which bounds the work done in a single update
+const MAX_EVENT_BATCH_SIZE: usize = 100;
+///...or when this much time has passed. Short enough that interactive output (typing,
+///prompts) is rendered without a noticeable delay
+const MIN_EVENT_BATCH_DURATION: Duration = Duration::from_millis(4);
+///While output keeps filling whole batches, the time budget grows up to roughly one
+///frame at 60Hz, since rendering more often than that is wasted work. It drops back
+///to the minimum as soon as a batch comes in under the size limit
+const MAX_EVENT_BATCH_DURATION: Duration = Duration::from_millis(16);
 
 ///Upward flowing events, for changing the title and such
 #[derive(Clone, Debug)]
 pub enum Event {
     TitleChanged,
+    /// The working directory of the foreground process changed.
+    CwdChanged(PathBuf),
     BreadcrumbsChanged,
     CloseTerminal,
+    /// The process spawned in the terminal, usually the shell, exited with the given
+    /// status code. Commands run from the shell don't emit this when they finish.
+    ProcessExited(i32),
     Bell,
     Wakeup,
     BlinkChanged,
     SelectionsChanged,
+    /// The stored search matches were replaced or cleared.
+    MatchesChanged,
+    /// A resize has been applied to the grid and sent to the PTY.
+    Resized(TerminalSize),
     NewNavigationTarget(Option<MaybeNavigationTarget>),
     Open(MaybeNavigationTarget),
 }
@@ -130,22 +171,36 @@ enum InternalEvent {
     ColorRequest(usize, Arc<dyn Fn(Rgb) -> String + Sync + Send + 'static>),
     Resize(TerminalSize),
     Clear,
+    ClearScrollback,
+    Reset,
     // FocusNextMatch,
     Scroll(AlacScroll),
-    ScrollToAlacPoint(AlacPoint),
+    ScrollToAlacPoint(AlacPoint, ScrollAlignment),
     SetSelection(Option<(Selection, AlacPoint)>),
     UpdateSelection(Point<Pixels>),
+    ExtendSelection(AlacPoint),
     // Adjusted mouse position, should open
     FindHyperlink(Point<Pixels>, bool),
-    Copy,
+    Copy(CopyFormat),
+    // Reports whether the terminal gained (true) or lost focus, if the program asked for it
+    FocusReport(bool),
+    SetBlink(TerminalBlink),
 }
 
 ///A translation struct for Alacritty to communicate with us from their event loop
+///
+///Alacritty sends a wakeup after every read from the PTY, so a program flooding output
+///would queue wakeups faster than we can process them. All bytes are applied to the
+///grid regardless, so one pending wakeup is enough to render the latest state: further
+///ones are dropped until the pending one is received, keeping the queue bounded.
 #[derive(Clone)]
-pub struct ZedListener(UnboundedSender<AlacTermEvent>);
+pub struct ZedListener(UnboundedSender<AlacTermEvent>, Arc<AtomicBool>);
 
 impl EventListener for ZedListener {
     fn send_event(&self, event: AlacTermEvent) {
+        if matches!(event, AlacTermEvent::Wakeup) && self.1.swap(true, Ordering::AcqRel) {
+            return;
+        }
         self.0.unbounded_send(event).ok();
     }
 }
@@ -208,17 +263,42 @@ impl Default for TerminalSize {
     }
 }
 
+impl From<CursorShape> for AlacCursorShape {
+    fn from(value: CursorShape) -> Self {
+        match value {
+            CursorShape::Block => AlacCursorShape::Block,
+            CursorShape::Underline => AlacCursorShape::Underline,
+            CursorShape::Bar => AlacCursorShape::Beam,
+            CursorShape::Hollow => AlacCursorShape::HollowBlock,
+        }
+    }
+}
+
+/// The PTY is told its size in pixels as the cell count times these integer cell
+/// sizes, so they're rounded rather than truncated. That keeps the reported size
+/// within half a pixel per cell of the grid, at the cost of over-reporting it by up
+/// to that much when the fractional part is half a pixel or more, where truncating
+/// would under-report by up to a whole pixel per cell. Replies to CSI 14 t are exact,
+/// see `text_area_size_report`.
 impl From<TerminalSize> for WindowSize {
     fn from(val: TerminalSize) -> Self {
         WindowSize {
             num_lines: val.num_lines() as u16,
             num_cols: val.num_columns() as u16,
-            cell_width: f32::from(val.cell_width()) as u16,
-            cell_height: f32::from(val.line_height()) as u16,
+            cell_width: f32::from(val.cell_width()).round() as u16,
+            cell_height: f32::from(val.line_height()).round() as u16,
         }
     }
 }
 
+/// The reply to a text area size request in pixels (CSI 14 t), the grid's size rounded
+/// to whole pixels. Requests in cells (CSI 18 t) are answered by alacritty itself.
+fn text_area_size_report(size: &TerminalSize) -> String {
+    let width = (size.columns() as f32 * f32::from(size.cell_width)).round() as u32;
+    let height = (size.screen_lines() as f32 * f32::from(size.line_height)).round() as u32;
+    format!("\x1b[4;{height};{width}t")
+}
+
 impl Dimensions for TerminalSize {
     /// Note: this is supposed to be for the back buffer's length,
     /// but we exclusively use it to resize the terminal, which does not
@@ -272,7 +352,7 @@ impl TerminalError {
         match &self.shell {
             Shell::System => "<system shell>".to_string(),
             Shell::Program(p) => p.to_string(),
-            Shell::WithArguments { program, args } => format!("{} {}", program, args.join(" ")),
+            Shell::WithArguments { program, args } => quote_command(program, args),
         }
     }
 
@@ -280,11 +360,94 @@ impl TerminalError {
         match &self.shell {
             Shell::System => "<system defined shell>".to_string(),
             Shell::Program(s) => s.to_string(),
-            Shell::WithArguments { program, args } => format!("{} {}", program, args.join(" ")),
+            Shell::WithArguments { program, args } => quote_command(program, args),
+        }
+    }
+}
+
+/// The program alacritty spawns for `shell`, passing `-l` when `login_shell` is set.
+/// Shells given arguments are left to them, tasks included. On macOS the system
+/// shell is always a login shell, as alacritty starts it through `/usr/bin/login -flp`.
+fn alacritty_shell(
+    shell: Shell,
+    login_shell: bool,
+    system_shell: impl FnOnce() -> Option<String>,
+) -> Option<alacritty_terminal::tty::Shell> {
+    let login_args = || {
+        if login_shell && cfg!(unix) {
+            vec!["-l".to_string()]
+        } else {
+            Vec::new()
+        }
+    };
+    match shell {
+        Shell::System if cfg!(target_os = "macos") => None,
+        Shell::System => {
+            system_shell().map(|program| alacritty_terminal::tty::Shell::new(program, login_args()))
+        }
+        Shell::Program(program) => Some(alacritty_terminal::tty::Shell::new(program, login_args())),
+        Shell::WithArguments { program, args } => {
+            Some(alacritty_terminal::tty::Shell::new(program, args))
         }
     }
 }
 
+/// The user's login shell for `Shell::System`: `$SHELL`, or the shell of their passwd
+/// entry when it's unset. Without one, alacritty picks the shell itself.
+#[cfg(unix)]
+fn system_shell() -> Option<String> {
+    std::env::var("SHELL")
+        .ok()
+        .filter(|shell| !shell.is_empty())
+        .or_else(passwd_shell)
+}
+
+#[cfg(not(unix))]
+fn system_shell() -> Option<String> {
+    None
+}
+
+#[cfg(unix)]
+fn passwd_shell() -> Option<String> {
+    let buflen = match unsafe { libc::sysconf(libc::_SC_GETPW_R_SIZE_MAX) } {
+        n if n < 0 => 1024,
+        n => n as usize,
+    };
+    let mut buffer = vec![0 as libc::c_char; buflen];
+    let mut pwd = std::mem::MaybeUninit::<libc::passwd>::uninit();
+    let mut result: *mut libc::passwd = std::ptr::null_mut();
+    let status = unsafe {
+        libc::getpwuid_r(
+            libc::getuid(),
+            pwd.as_mut_ptr(),
+            buffer.as_mut_ptr(),
+            buflen,
+            &mut result,
+        )
+    };
+    if status != 0 || result.is_null() {
+        return None;
+    }
+    let entry = unsafe { pwd.assume_init() };
+    if entry.pw_shell.is_null() {
+        return None;
+    }
+    let shell = unsafe { std::ffi::CStr::from_ptr(entry.pw_shell) }
+        .to_str()
+        .ok()?;
+    (!shell.is_empty()).then(|| shell.to_string())
+}
+
+///Joins a program and its arguments, quoting them so that arguments containing spaces
+///can be told apart
+fn quote_command(program: &str, args: &[String]) -> String {
+    iter::once(program)
+        .chain(args.iter().map(String::as_str))
+        .map(|arg| shlex::try_quote(arg).unwrap_or(Cow::Borrowed(arg)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 impl Display for TerminalError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let dir_string: String = self.fmt_directory();
@@ -298,13 +461,109 @@ impl Display for TerminalError {
     }
 }
 
+// A path with a `:line` or `:line:column` suffix, as in compiler output, or one with a separator
+const PATH_REGEX: &str = r"[\w.\-~/\\]+(:\d+){1,2}|[\w.\-~]*[/\\][\w.\-~/\\]+";
+const URL_REGEX: &str = r#"(ipfs:|ipns:|magnet:|mailto:|gemini://|gopher://|https://|http://|news:|file://|git://|ssh:|ftp://)[^\u{0000}-\u{001F}\u{007F}-\u{009F}<>"\s{-}\^⟨⟩`]+"#;
+
 // https://github.com/alacritty/alacritty/blob/cb3a79dbf6472740daca8440d5166c1d4af5029e/extra/man/alacritty.5.scd?plain=1#L207-L213
 const DEFAULT_SCROLL_HISTORY_LINES: usize = 10_000;
 const MAX_SCROLL_HISTORY_LINES: usize = 100_000;
 
+const MAX_TITLE_CHARS: usize = 25;
+
+const FALLBACK_LOCALE: &str = "en_US.UTF-8";
+
+/// Whether the shell would start without a locale, either from the terminal's
+/// env or from the environment Zed itself was launched with. Apps started
+/// from the macOS Finder, for instance, usually inherit no locale at all.
+fn is_locale_unset(
+    env: &HashMap<String, String>,
+    host_var: impl Fn(&str) -> Option<String>,
+) -> bool {
+    ["LC_ALL", "LC_CTYPE", "LANG"].into_iter().all(|name| {
+        env.get(name)
+            .cloned()
+            .or_else(|| host_var(name))
+            .map_or(true, |value| value.is_empty())
+    })
+}
+
+/// Completes the env passed to the pty, on top of the environment inherited from Zed.
+/// Values in `env` override inherited ones, an empty value sets a variable to empty
+/// rather than unsetting it.
+fn terminal_env(
+    mut env: HashMap<String, String>,
+    host_var: impl Fn(&str) -> Option<String>,
+    app_version: String,
+) -> HashMap<String, String> {
+    if is_locale_unset(&env, host_var) {
+        env.insert("LC_ALL".to_string(), FALLBACK_LOCALE.to_string());
+    }
+
+    env.insert("ZED_TERM".to_string(), "true".to_string());
+    env.insert("TERM_PROGRAM".to_string(), "zed".to_string());
+    env.insert("TERM_PROGRAM_VERSION".to_string(), app_version);
+    env
+}
+
+fn pty_options(
+    shell: Shell,
+    login_shell: bool,
+    working_directory: Option<PathBuf>,
+    env: HashMap<String, String>,
+) -> alacritty_terminal::tty::Options {
+    alacritty_terminal::tty::Options {
+        shell: alacritty_shell(shell.clone(), login_shell, system_shell),
+        working_directory,
+        #[cfg(target_os = "linux")]
+        hold: !matches!(shell, Shell::System),
+        // with hold: true, macOS gets tasks stuck on ctrl-c interrupts periodically
+        #[cfg(not(target_os = "linux"))]
+        hold: false,
+        env: env.into_iter().collect(),
+    }
+}
+
+/// The settings a terminal is created with, see [`TerminalSettings`] for what they do.
+#[derive(Clone, Debug)]
+pub struct TerminalOptions {
+    pub login_shell: bool,
+    pub blink_settings: Option<TerminalBlink>,
+    pub alternate_scroll: AlternateScroll,
+    pub max_scroll_history_lines: Option<usize>,
+    pub scroll_multiplier: Option<f32>,
+    pub cursor_shape: CursorShape,
+    pub bell: TerminalBell,
+    pub scroll_on_input: bool,
+    pub title_source: TitleSource,
+    pub title_template: Option<String>,
+    pub word_separators: Option<String>,
+    pub keep_open_on_exit: KeepOpenOnExit,
+}
+
+impl From<&TerminalSettings> for TerminalOptions {
+    fn from(settings: &TerminalSettings) -> Self {
+        TerminalOptions {
+            login_shell: settings.login_shell,
+            blink_settings: Some(settings.blinking),
+            alternate_scroll: settings.alternate_scroll,
+            max_scroll_history_lines: settings.max_scroll_history_lines,
+            scroll_multiplier: settings.scroll_multiplier,
+            cursor_shape: settings.cursor_shape.unwrap_or_default(),
+            bell: settings.bell.unwrap_or_default(),
+            scroll_on_input: settings.scroll_on_input,
+            title_source: settings.title_source.unwrap_or_default(),
+            title_template: settings.title_template.clone(),
+            word_separators: settings.word_separators.clone(),
+            keep_open_on_exit: settings.keep_open_on_exit.unwrap_or_default(),
+        }
+    }
+}
+
 pub struct TerminalBuilder {
     terminal: Terminal,
     events_rx: UnboundedReceiver<AlacTermEvent>,
+    wakeup_pending: Arc<AtomicBool>,
 }
 
 impl TerminalBuilder {
@@ -313,47 +572,33 @@ impl TerminalBuilder {
         working_directory: Option<PathBuf>,
         task: Option<TaskState>,
         shell: Shell,
-        mut env: HashMap<String, String>,
-        blink_settings: Option<TerminalBlink>,
-        alternate_scroll: AlternateScroll,
-        max_scroll_history_lines: Option<usize>,
+        env: HashMap<String, String>,
+        options: TerminalOptions,
         window: AnyWindowHandle,
         completion_tx: Sender<()>,
         cx: &mut AppContext,
     ) -> Result<TerminalBuilder> {
-        // TODO: Properly set the current locale,
-        env.entry("LC_ALL".to_string())
-            .or_insert_with(|| "en_US.UTF-8".to_string());
-
-        env.insert("ZED_TERM".to_string(), "true".to_string());
-        env.insert("TERM_PROGRAM".to_string(), "zed".to_string());
-        env.insert(
-            "TERM_PROGRAM_VERSION".to_string(),
+        let env = terminal_env(
+            env,
+            |name| std::env::var(name).ok(),
             release_channel::AppVersion::global(cx).to_string(),
         );
 
-        let pty_options = {
-            let alac_shell = match shell.clone() {
-                Shell::System => None,
-                Shell::Program(program) => {
-                    Some(alacritty_terminal::tty::Shell::new(program, Vec::new()))
-                }
-                Shell::WithArguments { program, args } => {
-                    Some(alacritty_terminal::tty::Shell::new(program, args))
-                }
-            };
-
-            alacritty_terminal::tty::Options {
-                shell: alac_shell,
-                working_directory: working_directory.clone(),
-                #[cfg(target_os = "linux")]
-                hold: !matches!(shell.clone(), Shell::System),
-                // with hold: true, macOS gets tasks stuck on ctrl-c interrupts periodically
-                #[cfg(not(target_os = "linux"))]
-                hold: false,
-                env: env.into_iter().collect(),
-            }
-        };
+        let TerminalOptions {
+            login_shell,
+            blink_settings,
+            alternate_scroll,
+            max_scroll_history_lines,
+            scroll_multiplier,
+            cursor_shape,
+            bell,
+            scroll_on_input,
+            title_source,
+            title_template,
+            word_separators,
+            keep_open_on_exit,
+        } = options;
+        let pty_options = pty_options(shell.clone(), login_shell, working_directory.clone(), env);
 
         // Setup Alacritty's env
         setup_env();
@@ -368,19 +613,30 @@ impl TerminalBuilder {
                 .unwrap_or(DEFAULT_SCROLL_HISTORY_LINES)
                 .min(MAX_SCROLL_HISTORY_LINES)
         };
-        let config = Config {
+        let mut config = Config {
             scrolling_history,
+            // Programs can still change the shape via DECSCUSR, this is only the fallback.
+            default_cursor_style: AlacCursorStyle {
+                shape: cursor_shape.into(),
+                blinking: false,
+            },
+            // Lets programs opt into the kitty keyboard protocol, see `to_esc_str`
+            kitty_keyboard: true,
             ..Config::default()
         };
+        if let Some(word_separators) = word_separators {
+            config.semantic_escape_chars = word_separators;
+        }
 
         //Spawn a task so the Alacritty EventLoop can communicate with us in a view context
         //TODO: Remove with a bounded sender which can be dispatched on &self
         let (events_tx, events_rx) = unbounded();
+        let wakeup_pending = Arc::new(AtomicBool::new(false));
         //Set up the terminal...
         let mut term = Term::new(
             config,
             &TerminalSize::default(),
-            ZedListener(events_tx.clone()),
+            ZedListener(events_tx.clone(), wakeup_pending.clone()),
         );
 
         //Start off blinking if we need to
@@ -388,9 +644,12 @@ impl TerminalBuilder {
             term.set_private_mode(PrivateMode::Named(NamedPrivateMode::BlinkingCursor));
         }
 
-        //Alacritty defaults to alternate scrolling being on, so we just need to turn it off.
-        if let AlternateScroll::Off = alternate_scroll {
-            term.unset_private_mode(PrivateMode::Named(NamedPrivateMode::AlternateScroll));
+        //Alacritty currently starts with alternate scrolling on. Set the mode either way so
+        //the setting applies regardless of that default, programs can still change it.
+        let alternate_scroll_mode = PrivateMode::Named(NamedPrivateMode::AlternateScroll);
+        match alternate_scroll {
+            AlternateScroll::On => term.set_private_mode(alternate_scroll_mode),
+            AlternateScroll::Off => term.unset_private_mode(alternate_scroll_mode),
         }
 
         let term = Arc::new(FairMutex::new(term));
@@ -416,7 +675,7 @@ impl TerminalBuilder {
         //And connect them together
         let event_loop = EventLoop::new(
             term.clone(),
-            ZedListener(events_tx.clone()),
+            ZedListener(events_tx.clone(), wakeup_pending.clone()),
             pty,
             pty_options.hold,
             false,
@@ -426,7 +685,8 @@ impl TerminalBuilder {
         let pty_tx = event_loop.channel();
         let _io_thread = event_loop.spawn(); // DANGER
 
-        let url_regex = RegexSearch::new(r#"(ipfs:|ipns:|magnet:|mailto:|gemini://|gopher://|https://|http://|news:|file://|git://|ssh:|ftp://)[^\u{0000}-\u{001F}\u{007F}-\u{009F}<>"\s{-}\^⟨⟩`]+"#).unwrap();
+        let url_regex = RegexSearch::new(URL_REGEX).unwrap();
+        let path_regex = RegexSearch::new(PATH_REGEX).unwrap();
         let word_regex = RegexSearch::new(r#"[\$\+\w.\[\]:/\\@\-~]+"#).unwrap();
 
         let terminal = Terminal {
@@ -438,22 +698,48 @@ impl TerminalBuilder {
             last_content: Default::default(),
             last_mouse: None,
             matches: Vec::new(),
+            selected_match: None,
             selection_head: None,
             pty_info,
             breadcrumb_text: String::new(),
             scroll_px: px(0.),
+            horizontal_scroll_px: px(0.),
+            scroll_multiplier: scroll_multiplier
+                .unwrap_or(DEFAULT_SCROLL_MULTIPLIER)
+                .max(MIN_SCROLL_MULTIPLIER),
+            bell,
+            bell_flash_task: None,
+            pending_size: None,
+            resize_task: None,
+            process_info_task: None,
+            scroll_on_input,
+            title_source,
+            title_template,
+            exit_status: None,
+            content_generation: 0,
+            literal_search: None,
+            keep_open_on_exit,
+            vi_mode_cursor: None,
+            vi_mode_anchor: AlacPoint::default(),
+            follow: true,
+            follow_history_size: 0,
+            read_only: false,
+            hovered_point: None,
+            event_senders: Vec::new(),
             last_mouse_position: None,
             next_link_id: 0,
             selection_phase: SelectionPhase::Ended,
             secondary_pressed: false,
             hovered_word: false,
             url_regex,
+            path_regex,
             word_regex,
         };
 
         Ok(TerminalBuilder {
             terminal,
             events_rx,
+            wakeup_pending,
         })
     }
 
@@ -461,30 +747,30 @@ impl TerminalBuilder {
         //Event loop
         cx.spawn(|terminal, mut cx| async move {
             while let Some(event) = self.events_rx.next().await {
+                self.received(&event);
                 terminal.update(&mut cx, |terminal, cx| {
                     //Process the first event immediately for lowered latency
                     terminal.process_event(&event, cx);
                 })?;
 
+                let mut batch_duration = MIN_EVENT_BATCH_DURATION;
                 'outer: loop {
                     let mut events = Vec::new();
-                    let mut timer = cx
-                        .background_executor()
-                        .timer(Duration::from_millis(4))
-                        .fuse();
+                    let mut timer = cx.background_executor().timer(batch_duration).fuse();
                     let mut wakeup = false;
                     loop {
                         futures::select_biased! {
                             _ = timer => break,
                             event = self.events_rx.next() => {
                                 if let Some(event) = event {
+                                    self.received(&event);
                                     if matches!(event, AlacTermEvent::Wakeup) {
                                         wakeup = true;
                                     } else {
                                         events.push(event);
                                     }
 
-                                    if events.len() > 100 {
+                                    if events.len() >= MAX_EVENT_BATCH_SIZE {
                                         break;
                                     }
                                 } else {
@@ -499,6 +785,12 @@ impl TerminalBuilder {
                         break 'outer;
                     }
 
+                    batch_duration = if events.len() >= MAX_EVENT_BATCH_SIZE {
+                        (batch_duration * 2).min(MAX_EVENT_BATCH_DURATION)
+                    } else {
+                        MIN_EVENT_BATCH_DURATION
+                    };
+
                     terminal.update(&mut cx, |this, cx| {
                         if wakeup {
                             this.process_event(&AlacTermEvent::Wakeup, cx);
@@ -518,6 +810,13 @@ impl TerminalBuilder {
 
         self.terminal
     }
+
+    ///Lets the listener queue a wakeup again once the pending one has been received
+    fn received(&self, event: &AlacTermEvent) {
+        if matches!(event, AlacTermEvent::Wakeup) {
+            self.wakeup_pending.store(false, Ordering::Release);
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -526,6 +825,51 @@ pub struct IndexedCell {
     pub cell: Cell,
 }
 
+/// How `Terminal::copy_as` formats the selection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CopyFormat {
+    /// The text alone, as `copy` does
+    #[default]
+    Plain,
+    /// The text with the SGR sequences re-creating its colors and styles
+    Ansi,
+    /// A `<pre>` block of spans with inline styles
+    Html,
+}
+
+/// How a cell's text is underlined, as set by SGR 4 and its subparameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellUnderline {
+    Single,
+    Double,
+    Curly,
+    Dotted,
+    Dashed,
+}
+
+impl IndexedCell {
+    pub fn underline(&self) -> Option<CellUnderline> {
+        let flags = self.cell.flags;
+        if flags.contains(Flags::UNDERCURL) {
+            Some(CellUnderline::Curly)
+        } else if flags.contains(Flags::DOUBLE_UNDERLINE) {
+            Some(CellUnderline::Double)
+        } else if flags.contains(Flags::DOTTED_UNDERLINE) {
+            Some(CellUnderline::Dotted)
+        } else if flags.contains(Flags::DASHED_UNDERLINE) {
+            Some(CellUnderline::Dashed)
+        } else if flags.contains(Flags::UNDERLINE) {
+            Some(CellUnderline::Single)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_strikethrough(&self) -> bool {
+        self.cell.flags.contains(Flags::STRIKEOUT)
+    }
+}
+
 impl Deref for IndexedCell {
     type Target = Cell;
 
@@ -547,6 +891,15 @@ pub struct TerminalContent {
     pub cursor_char: char,
     pub size: TerminalSize,
     pub last_hovered_word: Option<HoveredWord>,
+    /// Explicit OSC 8 hyperlinks in the visible cells, as opposed to URLs detected by regex.
+    pub hyperlinks: Vec<(RangeInclusive<AlacPoint>, String)>,
+    /// Whether a visual bell is currently flashing.
+    pub bell_flash: bool,
+    /// The detected link (URL or file path) under the mouse, to underline.
+    pub hovered_match: Option<RangeInclusive<AlacPoint>>,
+    /// The viewport rows, 0 being the top one, whose cells changed in the last sync.
+    /// The cursor and selection are not part of the cells and aren't tracked here.
+    pub dirty_lines: Vec<usize>,
 }
 
 #[derive(Clone)]
@@ -565,14 +918,74 @@ impl Default for TerminalContent {
             selection_text: Default::default(),
             selection: Default::default(),
             cursor: RenderableCursor {
-                shape: alacritty_terminal::vte::ansi::CursorShape::Block,
+                shape: AlacCursorShape::Block,
                 point: AlacPoint::new(Line(0), Column(0)),
             },
             cursor_char: Default::default(),
             size: Default::default(),
             last_hovered_word: None,
+            hyperlinks: Vec::new(),
+            bell_flash: false,
+            hovered_match: None,
+            dirty_lines: Vec::new(),
+        }
+    }
+}
+
+impl TerminalContent {
+    /// The visible cells as text, one line per row. Gaps between cells are filled with
+    /// spaces and trailing whitespace is trimmed from each line.
+    pub fn visible_text(&self) -> String {
+        let mut text = String::new();
+        let mut line_start = 0;
+        let mut current_line = self.cells.first().map(|cell| cell.point.line);
+        let mut column = 0;
+        for cell in &self.cells {
+            if cell.flags.contains(Flags::WIDE_CHAR_SPACER) {
+                continue;
+            }
+            if let Some(line) = current_line.filter(|line| *line != cell.point.line) {
+                text.truncate(line_start + text[line_start..].trim_end().len());
+                for _ in 0..(cell.point.line.0 - line.0).max(1) {
+                    text.push('\n');
+                }
+                line_start = text.len();
+                column = 0;
+            }
+            current_line = Some(cell.point.line);
+
+            while column < cell.point.column.0 {
+                text.push(' ');
+                column += 1;
+            }
+            text.push(cell.c);
+            text.extend(cell.zerowidth().into_iter().flatten());
+            column += if cell.flags.contains(Flags::WIDE_CHAR) {
+                2
+            } else {
+                1
+            };
         }
+        text.truncate(text.trim_end().len());
+        text
     }
+
+    /// The visible cell at the grid point, if any.
+    pub fn cell_at(&self, point: AlacPoint) -> Option<&IndexedCell> {
+        let index = self
+            .cells
+            .binary_search_by_key(&point, |cell| cell.point)
+            .ok()?;
+        self.cells.get(index)
+    }
+}
+
+/// Where in the viewport to place content that is scrolled into view.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScrollAlignment {
+    Top,
+    Center,
+    Bottom,
 }
 
 #[derive(PartialEq, Eq)]
@@ -591,20 +1004,58 @@ pub struct Terminal {
     /// This is only used for terminal hovered word checking
     last_mouse_position: Option<Point<Pixels>>,
     pub matches: Vec<RangeInclusive<AlacPoint>>,
+    selected_match: Option<usize>,
     pub last_content: TerminalContent,
     pub selection_head: Option<AlacPoint>,
     pub breadcrumb_text: String,
     pub pty_info: PtyProcessInfo,
     scroll_px: Pixels,
+    horizontal_scroll_px: Pixels,
+    scroll_multiplier: f32,
+    bell: TerminalBell,
+    bell_flash_task: Option<Task<()>>,
+    pending_size: Option<TerminalSize>,
+    resize_task: Option<Task<()>>,
+    process_info_task: Option<Task<()>>,
+    scroll_on_input: bool,
+    title_source: TitleSource,
+    title_template: Option<String>,
+    exit_status: Option<i32>,
+    /// Incremented whenever the grid content may have changed
+    content_generation: usize,
+    literal_search: Option<LiteralSearch>,
+    keep_open_on_exit: KeepOpenOnExit,
+    /// The keyboard selection cursor, while selection mode is on
+    vi_mode_cursor: Option<AlacPoint>,
+    /// Where the keyboard selection started
+    vi_mode_anchor: AlacPoint,
+    /// Whether the viewport moves with new output, off while scroll-locked
+    follow: bool,
+    /// The history size as of the last sync, to hold a scroll-locked viewport in place
+    follow_history_size: usize,
+    /// Whether user input (typing, pasting) is ignored
+    read_only: bool,
+    /// The grid point under the mouse, see `set_hovered_point`
+    hovered_point: Option<AlacPoint>,
+    /// Senders for the `event_stream`s, dropped once their receiver is
+    event_senders: Vec<UnboundedSender<Event>>,
     next_link_id: usize,
     selection_phase: SelectionPhase,
     secondary_pressed: bool,
     hovered_word: bool,
     url_regex: RegexSearch,
+    path_regex: RegexSearch,
     word_regex: RegexSearch,
     task: Option<TaskState>,
 }
 
+/// The latest literal search, used to refine its matches when the query is extended.
+struct LiteralSearch {
+    pattern: String,
+    generation: usize,
+    matches: Vec<RangeInclusive<AlacPoint>>,
+}
+
 pub struct TaskState {
     pub id: TaskId,
     pub full_label: String,
@@ -646,55 +1097,151 @@ impl Terminal {
         match event {
             AlacTermEvent::Title(title) => {
                 self.breadcrumb_text = title.to_string();
-                cx.emit(Event::BreadcrumbsChanged);
+                self.emit(Event::BreadcrumbsChanged, cx);
             }
             AlacTermEvent::ResetTitle => {
                 self.breadcrumb_text = String::new();
-                cx.emit(Event::BreadcrumbsChanged);
+                self.emit(Event::BreadcrumbsChanged, cx);
             }
             AlacTermEvent::ClipboardStore(_, data) => {
-                cx.write_to_clipboard(ClipboardItem::new(data.to_string()))
+                if TerminalSettings::get_global(cx).allow_clipboard_write {
+                    cx.write_to_clipboard(ClipboardItem::new(data.to_string()))
+                }
             }
-            AlacTermEvent::ClipboardLoad(_, format) => self.write_to_pty(format(
-                &cx.read_from_clipboard()
-                    .map(|ci| ci.text().to_string())
-                    .unwrap_or_else(|| "".to_string()),
-            )),
+            AlacTermEvent::ClipboardLoad(_, format) => {
+                let clipboard = if TerminalSettings::get_global(cx).allow_clipboard_read {
+                    cx.read_from_clipboard()
+                } else {
+                    None
+                };
+                self.write_to_pty(format(
+                    &clipboard
+                        .map(|ci| ci.text().to_string())
+                        .unwrap_or_else(|| "".to_string()),
+                ))
+            }
+            // Also carries alacritty's answers to queries like DECRQM mode requests
             AlacTermEvent::PtyWrite(out) => self.write_to_pty(out.clone()),
-            AlacTermEvent::TextAreaSizeRequest(format) => {
-                self.write_to_pty(format(self.last_content.size.into()))
+            // Alacritty's own reply would multiply the integer cell sizes of a `WindowSize`
+            AlacTermEvent::TextAreaSizeRequest(_) => {
+                self.write_to_pty(text_area_size_report(&self.last_content.size))
             }
             AlacTermEvent::CursorBlinkingChange => {
-                cx.emit(Event::BlinkChanged);
-            }
-            AlacTermEvent::Bell => {
-                cx.emit(Event::Bell);
+                self.emit(Event::BlinkChanged, cx);
             }
+            AlacTermEvent::Bell => match self.bell {
+                TerminalBell::Off => {}
+                TerminalBell::Visual => self.flash_bell(cx),
+                TerminalBell::Audible => self.emit(Event::Bell, cx),
+            },
             AlacTermEvent::Exit => self.register_task_finished(None, cx),
             AlacTermEvent::MouseCursorDirty => {
                 //NOOP, Handled in render
             }
             AlacTermEvent::Wakeup => {
-                cx.emit(Event::Wakeup);
-
-                if self.pty_info.has_changed() {
-                    cx.emit(Event::TitleChanged);
-                }
+                self.content_generation = self.content_generation.wrapping_add(1);
+                self.emit(Event::Wakeup, cx);
+                self.check_process_info(cx);
             }
             AlacTermEvent::ColorRequest(idx, fun_ptr) => {
                 self.events
                     .push_back(InternalEvent::ColorRequest(*idx, fun_ptr.clone()));
             }
             AlacTermEvent::ChildExit(error_code) => {
+                self.exit_status = Some(*error_code);
+                self.emit(Event::ProcessExited(*error_code), cx);
                 self.register_task_finished(Some(*error_code), cx);
             }
         }
     }
 
+    /// The exit status of the process spawned in the terminal, once it has exited.
+    pub fn exit_status(&self) -> Option<i32> {
+        self.exit_status
+    }
+
     pub fn selection_started(&self) -> bool {
         self.selection_phase == SelectionPhase::Selecting
     }
 
+    fn flash_bell(&mut self, cx: &mut ModelContext<Self>) {
+        self.last_content.bell_flash = true;
+        self.emit(Event::Wakeup, cx);
+        self.bell_flash_task = Some(cx.spawn(|terminal, mut cx| async move {
+            cx.background_executor().timer(BELL_FLASH_DURATION).await;
+            terminal
+                .update(&mut cx, |terminal, cx| {
+                    terminal.last_content.bell_flash = false;
+                    terminal.bell_flash_task = None;
+                    terminal.emit(Event::Wakeup, cx);
+                })
+                .ok();
+        }));
+    }
+
+    /// Resizes the grid and the PTY at most once per [`RESIZE_DEBOUNCE`], to the latest
+    /// size requested, so that a live window drag doesn't make programs redraw on every frame.
+    fn schedule_resize(&mut self, new_size: TerminalSize, cx: &mut ModelContext<Self>) {
+        self.pending_size = Some(new_size);
+        if self.resize_task.is_some() {
+            return;
+        }
+
+        self.resize_task = Some(cx.spawn(|terminal, mut cx| async move {
+            cx.background_executor().timer(RESIZE_DEBOUNCE).await;
+            terminal
+                .update(&mut cx, |terminal, cx| {
+                    terminal.resize_task = None;
+                    if let Some(size) = terminal.pending_size.take() {
+                        terminal.resize(size, cx);
+                    }
+                })
+                .ok();
+        }));
+    }
+
+    fn resize(&mut self, new_size: TerminalSize, cx: &mut ModelContext<Self>) {
+        // Points of earlier matches may be stale in the rewrapped grid
+        self.content_generation = self.content_generation.wrapping_add(1);
+        self.last_content.size = new_size;
+        self.term.lock().resize(new_size);
+        self.pty_tx.0.send(Msg::Resize(new_size.into())).ok();
+        self.emit(Event::Resized(new_size), cx);
+        self.emit(Event::Wakeup, cx);
+    }
+
+    /// Emits title and cwd changes of the foreground process. Checks are rate limited,
+    /// so a throttled check is retried once the limit has passed, otherwise the last
+    /// change before the output goes quiet would never be picked up.
+    fn check_process_info(&mut self, cx: &mut ModelContext<Self>) {
+        if let Some(delay) = self.pty_info.throttled_for() {
+            if self.process_info_task.is_none() {
+                self.process_info_task = Some(cx.spawn(|terminal, mut cx| async move {
+                    cx.background_executor().timer(delay).await;
+                    terminal
+                        .update(&mut cx, |terminal, cx| {
+                            terminal.process_info_task = None;
+                            terminal.check_process_info(cx);
+                        })
+                        .ok();
+                }));
+            }
+            return;
+        }
+
+        let old_cwd = self.get_cwd();
+        if self.pty_info.has_changed() {
+            self.emit(Event::TitleChanged, cx);
+
+            let new_cwd = self.get_cwd();
+            if new_cwd != old_cwd {
+                if let Some(new_cwd) = new_cwd {
+                    self.emit(Event::CwdChanged(new_cwd), cx);
+                }
+            }
+        }
+    }
+
     pub fn get_cwd(&self) -> Option<PathBuf> {
         self.pty_info.current.as_ref().map(|info| info.cwd.clone())
     }
@@ -706,6 +1253,14 @@ impl Terminal {
         term: &mut Term<ZedListener>,
         cx: &mut ModelContext<Self>,
     ) {
+        if matches!(
+            event,
+            InternalEvent::Clear | InternalEvent::ClearScrollback | InternalEvent::Reset
+        ) {
+            // These rewrite the grid, so points of earlier matches may be stale
+            self.content_generation = self.content_generation.wrapping_add(1);
+        }
+
         match event {
             InternalEvent::ColorRequest(index, format) => {
                 let color = term.colors()[*index].unwrap_or_else(|| {
@@ -717,11 +1272,7 @@ impl Terminal {
                 new_size.size.height = cmp::max(new_size.line_height, new_size.height());
                 new_size.size.width = cmp::max(new_size.cell_width, new_size.width());
 
-                self.last_content.size = new_size;
-
-                self.pty_tx.0.send(Msg::Resize(new_size.into())).ok();
-
-                term.resize(new_size);
+                self.schedule_resize(new_size, cx);
             }
             InternalEvent::Clear => {
                 // Clear back buffer
@@ -753,11 +1304,32 @@ impl Terminal {
                     term.grid_mut().reset_region((new_cursor.line + 1)..);
                 }
 
-                cx.emit(Event::Wakeup);
+                self.emit(Event::Wakeup, cx);
+            }
+            InternalEvent::ClearScrollback => {
+                // Only drops the history, the visible screen is left untouched
+                term.clear_screen(ClearMode::Saved);
+                term.scroll_display(AlacScroll::Bottom);
+
+                self.emit(Event::Wakeup, cx);
+            }
+            InternalEvent::Reset => {
+                term.reset_state();
+                term.scroll_display(AlacScroll::Bottom);
+                self.matches.clear();
+                self.selected_match = None;
+                self.emit(Event::MatchesChanged, cx);
+
+                self.emit(Event::Wakeup, cx);
             }
             InternalEvent::Scroll(scroll) => {
+                let old_display_offset = term.grid().display_offset();
                 term.scroll_display(*scroll);
-                self.refresh_hovered_word();
+                // Scrolling past an edge (e.g. `Top` when already at the top) leaves
+                // the viewport untouched, so there is nothing to refresh.
+                if term.grid().display_offset() != old_display_offset {
+                    self.refresh_hovered_word();
+                }
             }
             InternalEvent::SetSelection(selection) => {
                 term.selection = selection.as_ref().map(|(sel, _)| sel.clone());
@@ -770,37 +1342,56 @@ impl Terminal {
                 if let Some((_, head)) = selection {
                     self.selection_head = Some(*head);
                 }
-                cx.emit(Event::SelectionsChanged)
+                self.emit(Event::SelectionsChanged, cx)
             }
             InternalEvent::UpdateSelection(position) => {
-                if let Some(mut selection) = term.selection.take() {
-                    let (point, side) = grid_point_and_side(
-                        *position,
-                        self.last_content.size,
-                        term.grid().display_offset(),
-                    );
-
-                    selection.update(point, side);
-                    term.selection = Some(selection);
-
-                    #[cfg(target_os = "linux")]
-                    if let Some(selection_text) = term.selection_to_string() {
-                        cx.write_to_primary(ClipboardItem::new(selection_text));
-                    }
-
-                    self.selection_head = Some(point);
-                    cx.emit(Event::SelectionsChanged)
-                }
+                let (point, side) = grid_point_and_side(
+                    *position,
+                    self.last_content.size,
+                    term.grid().display_offset(),
+                );
+                let (point, side) = wide_char_point_and_side(point, side, &self.last_content);
+                self.update_selection(term, point, side, cx);
+            }
+            InternalEvent::ExtendSelection(point) => {
+                self.update_selection(term, *point, Side::Right, cx);
             }
 
-            InternalEvent::Copy => {
-                if let Some(txt) = term.selection_to_string() {
+            InternalEvent::Copy(format) => {
+                if let Some(txt) = formatted_selection(term, *format, cx.theme()) {
                     cx.write_to_clipboard(ClipboardItem::new(txt))
                 }
             }
-            InternalEvent::ScrollToAlacPoint(point) => {
-                term.scroll_to_point(*point);
-                self.refresh_hovered_word();
+            InternalEvent::SetBlink(blink) => match blink {
+                TerminalBlink::On => {
+                    term.set_private_mode(PrivateMode::Named(NamedPrivateMode::BlinkingCursor))
+                }
+                TerminalBlink::Off => {
+                    term.unset_private_mode(PrivateMode::Named(NamedPrivateMode::BlinkingCursor))
+                }
+                // Left to whatever the program set
+                TerminalBlink::TerminalControlled => {}
+            },
+            InternalEvent::FocusReport(focused) => {
+                if let Some(report) = focus_report(*focused, *term.mode()) {
+                    self.write_to_pty(report.to_string());
+                }
+            }
+            InternalEvent::ScrollToAlacPoint(point, alignment) => {
+                let display_offset = term.grid().display_offset();
+                let screen_lines = term.screen_lines();
+                let line = point.line.0;
+                let visible = -(display_offset as i32) <= line
+                    && line < screen_lines as i32 - display_offset as i32;
+
+                if !visible {
+                    let new_display_offset =
+                        aligned_display_offset(line, screen_lines, term.history_size(), *alignment);
+                    term.scroll_display(AlacScroll::Delta(
+                        new_display_offset as i32 - display_offset as i32,
+                    ));
+                    self.refresh_hovered_word();
+                }
             }
             InternalEvent::FindHyperlink(position, open) => {
                 let prev_hovered_word = self.last_content.last_hovered_word.take();
@@ -888,7 +1479,7 @@ impl Terminal {
                                     terminal_dir: self.get_cwd(),
                                 })
                             };
-                            cx.emit(Event::Open(target));
+                            self.emit(Event::Open(target), cx);
                         } else {
                             self.update_selected_word(
                                 prev_hovered_word,
@@ -902,7 +1493,7 @@ impl Terminal {
                     }
                     None => {
                         if self.hovered_word {
-                            cx.emit(Event::NewNavigationTarget(None));
+                            self.emit(Event::NewNavigationTarget(None), cx);
                         }
                         self.hovered_word = false;
                     }
@@ -943,7 +1534,7 @@ impl Terminal {
                 terminal_dir: self.get_cwd(),
             })
         };
-        cx.emit(Event::NewNavigationTarget(Some(navigation_target)));
+        self.emit(Event::NewNavigationTarget(Some(navigation_target)), cx);
     }
 
     fn next_link_id(&mut self) -> usize {
@@ -956,6 +1547,97 @@ impl Terminal {
         &self.last_content
     }
 
+    /// The cell at the grid point as of the last sync, with its character, colors and
+    /// flags, for inspecting how it was rendered.
+    pub fn cell_at(&self, point: AlacPoint) -> Option<IndexedCell> {
+        self.last_content.cell_at(point).cloned()
+    }
+
+    /// The whole URL under the grid point, from an explicit hyperlink or detected in the
+    /// text, for the view to open with the platform opener.
+    pub fn open_url_at(&mut self, point: AlacPoint) -> Option<String> {
+        let term = self.term.clone();
+        let term = term.lock_unfair();
+        url_at(&term, point, &mut self.url_regex)
+    }
+
+    /// The word under the grid point and its range, split on the same characters as
+    /// semantic (double-click) selection, for lookups and highlighting.
+    pub fn word_at(&self, point: AlacPoint) -> Option<(String, RangeInclusive<AlacPoint>)> {
+        word_at(&self.term.lock_unfair(), point)
+    }
+
+    /// The file location under the grid point, like `src/main.rs:42:10` in compiler output,
+    /// as a path and a (line, column) position, with column 1 if only the line is given.
+    /// Relative paths are resolved against the working directory of the foreground process.
+    pub fn path_match_at(&mut self, point: AlacPoint) -> Option<(PathBuf, Option<(u32, u32)>)> {
+        let path_with_position = {
+            let term = self.term.clone();
+            let term = term.lock_unfair();
+            path_at(&term, point, &mut self.path_regex)?
+        };
+        let path = match self.get_cwd() {
+            Some(cwd) if path_with_position.path.is_relative() => {
+                cwd.join(&path_with_position.path)
+            }
+            _ => path_with_position.path,
+        };
+        let position = path_with_position
+            .row
+            .map(|row| (row, path_with_position.column.unwrap_or(1)));
+        Some((path, position))
+    }
+
+    /// Finds the link (explicit hyperlink, URL or file path) at the grid point for the
+    /// element to underline, see `TerminalContent::hovered_match`. `None` clears it, for
+    /// when the mouse leaves the terminal.
+    pub fn set_hovered_point(&mut self, point: Option<AlacPoint>) {
+        if self.hovered_point == point {
+            return;
+        }
+        self.hovered_point = point;
+        let term = self.term.clone();
+        self.refresh_hovered_match(&term.lock_unfair());
+    }
+
+    fn refresh_hovered_match(&mut self, term: &Term<ZedListener>) {
+        self.last_content.hovered_match = self.hovered_point.and_then(|point| {
+            let point = point.grid_clamp(term, Boundary::Grid);
+            self.last_content
+                .hyperlinks
+                .iter()
+                .find(|(range, _)| range.contains(&point))
+                .map(|(range, _)| range.clone())
+                .or_else(|| regex_match_at(term, point, &mut self.url_regex))
+                .or_else(|| regex_match_at(term, point, &mut self.path_regex))
+        });
+    }
+
+    /// The size as of the last sync, including resizes not yet sent to the PTY.
+    pub fn size(&self) -> TerminalSize {
+        self.last_content.size
+    }
+
+    /// The number of (lines, columns) in the grid.
+    pub fn grid_dimensions(&self) -> (usize, usize) {
+        let size = self.size();
+        (size.num_lines(), size.num_columns())
+    }
+
+    /// How many lines the viewport is scrolled up from the bottom.
+    pub fn display_offset(&self) -> usize {
+        self.last_content.display_offset
+    }
+
+    pub fn is_scrolled_to_bottom(&self) -> bool {
+        self.display_offset() == 0
+    }
+
+    /// Whether a full-screen program (an editor, a pager) has switched to the alternate screen.
+    pub fn is_alternate_screen(&self) -> bool {
+        self.last_content.mode.contains(TermMode::ALT_SCREEN)
+    }
+
     pub fn total_lines(&self) -> usize {
         let term = self.term.clone();
         let terminal = term.lock_unfair();
@@ -974,13 +1656,84 @@ impl Terminal {
 
     pub fn activate_match(&mut self, index: usize) {
         if let Some(search_match) = self.matches.get(index).cloned() {
+            self.selected_match = Some(index);
             self.set_selection(Some((make_selection(&search_match), *search_match.end())));
 
-            self.events
-                .push_back(InternalEvent::ScrollToAlacPoint(*search_match.start()));
+            self.scroll_match_into_view(&search_match, ScrollAlignment::Center);
         }
     }
 
+    /// Scrolls the match into view if it is off-screen, placing it according to `alignment`.
+    pub fn scroll_match_into_view(
+        &mut self,
+        range: &RangeInclusive<AlacPoint>,
+        alignment: ScrollAlignment,
+    ) {
+        let point = match alignment {
+            ScrollAlignment::Top | ScrollAlignment::Center => *range.start(),
+            ScrollAlignment::Bottom => *range.end(),
+        };
+        self.events
+            .push_back(InternalEvent::ScrollToAlacPoint(point, alignment));
+    }
+
+    /// Stores matches returned from `find_matches`, notifying observers.
+    pub fn set_matches(
+        &mut self,
+        matches: Vec<RangeInclusive<AlacPoint>>,
+        cx: &mut ModelContext<Self>,
+    ) {
+        self.matches = matches;
+        self.selected_match = None;
+        self.emit(Event::MatchesChanged, cx);
+    }
+
+    pub fn clear_matches(&mut self, cx: &mut ModelContext<Self>) {
+        self.set_matches(Vec::new(), cx);
+    }
+
+    pub fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    /// The index of the last match activated, if it is still in range of the current matches.
+    pub fn selected_match(&self) -> Option<usize> {
+        self.selected_match
+            .filter(|index| *index < self.matches.len())
+    }
+
+    pub fn activate_next_match(&mut self) {
+        let match_count = self.match_count();
+        if match_count == 0 {
+            return;
+        }
+        let index = match self.selected_match() {
+            Some(index) => (index + 1) % match_count,
+            None => 0,
+        };
+        self.activate_match(index);
+    }
+
+    pub fn activate_prev_match(&mut self) {
+        let match_count = self.match_count();
+        if match_count == 0 {
+            return;
+        }
+        let index = match self.selected_match() {
+            Some(index) => (index + match_count - 1) % match_count,
+            None => match_count - 1,
+        };
+        self.activate_match(index);
+    }
+
+    /// Returns the URLs in the visible part of the terminal, along with their grid ranges.
+    /// URLs soft-wrapped across several lines are returned as a single match.
+    pub fn visible_urls(&mut self) -> Vec<(RangeInclusive<AlacPoint>, String)> {
+        let term = self.term.clone();
+        let term = term.lock_unfair();
+        visible_regex_matches_with_text(&term, &mut self.url_regex)
+    }
+
     pub fn select_matches(&mut self, matches: &[RangeInclusive<AlacPoint>]) {
         let matches_to_select = self
             .matches
@@ -1004,19 +1757,107 @@ impl Terminal {
         self.set_selection(Some((make_selection(&(start..=end)), end)));
     }
 
+    /// Drops the selection without touching the PTY, unlike typing.
+    pub fn clear_selection(&mut self) {
+        self.selection_head = None;
+        self.set_selection(None);
+    }
+
+    fn update_selection(
+        &mut self,
+        term: &mut Term<ZedListener>,
+        point: AlacPoint,
+        side: Side,
+        cx: &mut ModelContext<Self>,
+    ) {
+        if let Some(mut selection) = term.selection.take() {
+            selection.update(point, side);
+            term.selection = Some(selection);
+
+            #[cfg(target_os = "linux")]
+            if let Some(selection_text) = term.selection_to_string() {
+                cx.write_to_primary(ClipboardItem::new(selection_text));
+            }
+
+            self.selection_head = Some(point);
+            self.emit(Event::SelectionsChanged, cx)
+        }
+    }
+
+    /// The selected range as of the last sync.
+    pub fn selection_range(&self) -> Option<SelectionRange> {
+        self.last_content.selection
+    }
+
+    /// Moves the end of the current selection to the grid point, including its cell.
+    /// Does nothing when there is no selection.
+    pub fn extend_selection_to(&mut self, point: AlacPoint) {
+        self.events.push_back(InternalEvent::ExtendSelection(point));
+    }
+
+    /// Starts selecting with the keyboard from the terminal cursor, or stops and clears
+    /// the selection if already selecting.
+    pub fn toggle_selection_mode(&mut self) {
+        if self.vi_mode_cursor.take().is_some() {
+            self.set_selection(None);
+        } else {
+            let point = self.last_content.cursor.point;
+            self.vi_mode_cursor = Some(point);
+            self.vi_mode_anchor = point;
+            self.set_selection(Some((make_selection(&(point..=point)), point)));
+        }
+    }
+
+    pub fn vi_mode_cursor(&self) -> Option<AlacPoint> {
+        self.vi_mode_cursor
+    }
+
+    /// Moves the keyboard selection cursor, selecting from where selection mode started.
+    pub fn move_vi_cursor(&mut self, motion: ViMotion) {
+        let Some(cursor) = self.vi_mode_cursor else {
+            return;
+        };
+        let cursor = {
+            let term = self.term.clone();
+            let mut term = term.lock_unfair();
+            ViModeCursor::new(cursor).motion(&mut term, motion).point
+        };
+        self.vi_mode_cursor = Some(cursor);
+
+        let range = cmp::min(self.vi_mode_anchor, cursor)..=cmp::max(self.vi_mode_anchor, cursor);
+        self.set_selection(Some((make_selection(&range), cursor)));
+        self.scroll_match_into_view(&(cursor..=cursor), ScrollAlignment::Center);
+    }
+
     fn set_selection(&mut self, selection: Option<(Selection, AlacPoint)>) {
         self.events
             .push_back(InternalEvent::SetSelection(selection));
     }
 
     pub fn copy(&mut self) {
-        self.events.push_back(InternalEvent::Copy);
+        self.copy_as(CopyFormat::Plain);
+    }
+
+    /// Copies the selection with its colors and styles, see `CopyFormat`.
+    pub fn copy_as(&mut self, format: CopyFormat) {
+        self.events.push_back(InternalEvent::Copy(format));
     }
 
     pub fn clear(&mut self) {
         self.events.push_back(InternalEvent::Clear)
     }
 
+    ///Drop the scrollback history, keeping the visible screen
+    pub fn clear_scrollback(&mut self) {
+        self.events.push_back(InternalEvent::ClearScrollback)
+    }
+
+    ///Hard reset the terminal state, like the `reset` command,
+    ///as opposed to `clear` which only clears the screen
+    pub fn reset(&mut self) {
+        self.events.push_back(InternalEvent::Reset)
+    }
+
     pub fn scroll_line_up(&mut self) {
         self.events
             .push_back(InternalEvent::Scroll(AlacScroll::Delta(1)));
@@ -1038,13 +1879,23 @@ impl Terminal {
     }
 
     pub fn scroll_page_up(&mut self) {
+        // Doesn't make sense to scroll the alt screen
+        if self.is_alternate_screen() {
+            return;
+        }
+        let lines = self.last_content.size.num_lines() as i32;
         self.events
-            .push_back(InternalEvent::Scroll(AlacScroll::PageUp));
+            .push_back(InternalEvent::Scroll(AlacScroll::Delta(lines)));
     }
 
     pub fn scroll_page_down(&mut self) {
+        // Doesn't make sense to scroll the alt screen
+        if self.is_alternate_screen() {
+            return;
+        }
+        let lines = self.last_content.size.num_lines() as i32;
         self.events
-            .push_back(InternalEvent::Scroll(AlacScroll::PageDown));
+            .push_back(InternalEvent::Scroll(AlacScroll::Delta(-lines)));
     }
 
     pub fn scroll_to_top(&mut self) {
@@ -1057,9 +1908,9 @@ impl Terminal {
             .push_back(InternalEvent::Scroll(AlacScroll::Bottom));
     }
 
-    ///Resize the terminal and the PTY.
+    ///Resize the terminal and the PTY, once resizing has settled.
     pub fn set_size(&mut self, new_size: TerminalSize) {
-        if self.last_content.size != new_size {
+        if self.pending_size.unwrap_or(self.last_content.size) != new_size {
             self.events.push_back(InternalEvent::Resize(new_size))
         }
     }
@@ -1073,23 +1924,86 @@ impl Terminal {
         self.pty_tx.notify(input);
     }
 
+    ///Write text to the PTY without the scrolling and selection side effects
+    ///of `input`, so that automated input doesn't disturb the user
+    pub fn send_text(&mut self, text: &str) {
+        self.write_to_pty(text.to_string());
+    }
+
+    ///Write a command line to the shell, as if it was typed and submitted
+    pub fn write_command_line(&mut self, command: String) {
+        self.send_text(&format!("{command}\n"));
+    }
+
+    /// Turns scroll-lock off (`true`), snapping to the bottom, or on (`false`), keeping
+    /// the viewport still as output arrives and when typing.
+    pub fn set_follow(&mut self, follow: bool) {
+        self.follow = follow;
+        if follow {
+            self.scroll_to_bottom();
+        } else {
+            hold_viewport(&mut self.term.lock());
+        }
+    }
+
+    pub fn is_following(&self) -> bool {
+        self.follow
+    }
+
+    /// A stream of the events emitted from now on, for consumers that don't
+    /// have a gpui context to subscribe with.
+    pub fn event_stream(&mut self) -> impl futures::Stream<Item = Event> {
+        let (tx, rx) = unbounded();
+        self.event_senders.push(tx);
+        rx
+    }
+
+    fn emit(&mut self, event: Event, cx: &mut ModelContext<Self>) {
+        self.event_senders
+            .retain(|sender| sender.unbounded_send(event.clone()).is_ok());
+        cx.emit(event);
+    }
+
+    /// Ignores typing and pasting while set, for terminals that only display output.
+    /// Scrolling, selecting and copying still work.
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    pub fn read_only(&self) -> bool {
+        self.read_only
+    }
+
     pub fn input(&mut self, input: String) {
-        self.events
-            .push_back(InternalEvent::Scroll(AlacScroll::Bottom));
+        if self.read_only {
+            return;
+        }
+        if self.scroll_on_input && self.follow {
+            self.events
+                .push_back(InternalEvent::Scroll(AlacScroll::Bottom));
+        }
         self.events.push_back(InternalEvent::SetSelection(None));
 
         self.write_to_pty(input);
     }
 
     pub fn input_bytes(&mut self, input: Vec<u8>) {
-        self.events
-            .push_back(InternalEvent::Scroll(AlacScroll::Bottom));
+        if self.read_only {
+            return;
+        }
+        if self.scroll_on_input && self.follow {
+            self.events
+                .push_back(InternalEvent::Scroll(AlacScroll::Bottom));
+        }
         self.events.push_back(InternalEvent::SetSelection(None));
 
         self.write_bytes_to_pty(input);
     }
 
     pub fn try_keystroke(&mut self, keystroke: &Keystroke, alt_is_meta: bool) -> bool {
+        if self.read_only {
+            return false;
+        }
         let esc = to_esc_str(keystroke, &self.last_content.mode, alt_is_meta);
         if let Some(esc) = esc {
             self.input(esc);
@@ -1108,32 +2022,57 @@ impl Terminal {
         changed
     }
 
+    ///Whether pasting the text would run it, as it contains newlines and
+    ///the running program doesn't use bracketed paste
+    pub fn paste_would_execute(&self, text: &str) -> bool {
+        !self.last_content.mode.contains(TermMode::BRACKETED_PASTE) && text.contains(['\n', '\r'])
+    }
+
     ///Paste text into the terminal
     pub fn paste(&mut self, text: &str) {
-        let paste_text = if self.last_content.mode.contains(TermMode::BRACKETED_PASTE) {
-            format!("{}{}{}", "\x1b[200~", text.replace('\x1b', ""), "\x1b[201~")
-        } else {
-            text.replace("\r\n", "\r").replace('\n', "\r")
-        };
-
-        self.input(paste_text);
+        let bracketed = self.last_content.mode.contains(TermMode::BRACKETED_PASTE);
+        self.input(paste_text(text, bracketed));
     }
 
     pub fn sync(&mut self, cx: &mut ModelContext<Self>) {
         let term = self.term.clone();
         let mut terminal = term.lock_unfair();
+        // Alacritty keeps a scrolled up viewport in place, but follows output at the bottom
+        if !self.follow && terminal.grid().display_offset() == 0 {
+            let new_lines = terminal
+                .grid()
+                .history_size()
+                .saturating_sub(self.follow_history_size);
+            if new_lines > 0 {
+                terminal.scroll_display(AlacScroll::Delta(new_lines as i32));
+            }
+        }
+
         //Note that the ordering of events matters for event processing
+        coalesce_events(&mut self.events);
         while let Some(e) = self.events.pop_front() {
             self.process_terminal_event(&e, &mut terminal, cx)
         }
-
-        self.last_content = Self::make_content(&terminal, &self.last_content);
+        self.follow_history_size = terminal.grid().history_size();
+
+        Self::make_content(&terminal, &mut self.last_content);
+        // The link under the mouse may have changed with the content, and isn't
+        // underlined while the program gets the mouse reports
+        if self.wants_mouse() {
+            self.hovered_point = None;
+            self.last_content.hovered_match = None;
+        } else if !self.last_content.dirty_lines.is_empty() {
+            self.refresh_hovered_match(&terminal);
+        }
     }
 
-    fn make_content(term: &Term<ZedListener>, last_content: &TerminalContent) -> TerminalContent {
+    /// Refreshes `last_content` from the terminal, refilling its cell buffer in place
+    /// so that frequent syncs don't reallocate it.
+    fn make_content(term: &Term<ZedListener>, last_content: &mut TerminalContent) {
         let content = term.renderable_content();
-        TerminalContent {
-            cells: content
+        last_content.dirty_lines = update_cells(
+            &mut last_content.cells,
+            content
                 .display_iter
                 //TODO: Add this once there's a way to retain empty lines
                 // .filter(|ic| {
@@ -1145,17 +2084,16 @@ impl Terminal {
                 .map(|ic| IndexedCell {
                     point: ic.point,
                     cell: ic.cell.clone(),
-                })
-                .collect::<Vec<IndexedCell>>(),
-            mode: content.mode,
-            display_offset: content.display_offset,
-            selection_text: term.selection_to_string(),
-            selection: content.selection,
-            cursor: content.cursor,
-            cursor_char: term.grid()[content.cursor.point].c,
-            size: last_content.size,
-            last_hovered_word: last_content.last_hovered_word.clone(),
-        }
+                }),
+            term.columns(),
+        );
+        last_content.hyperlinks = hyperlinks_in_cells(&last_content.cells);
+        last_content.mode = content.mode;
+        last_content.display_offset = content.display_offset;
+        last_content.selection_text = term.selection_to_string();
+        last_content.selection = content.selection;
+        last_content.cursor = content.cursor;
+        last_content.cursor_char = term.grid()[content.cursor.point].c;
     }
 
     pub fn last_n_non_empty_lines(&self, n: usize) -> Vec<String> {
@@ -1183,17 +2121,32 @@ impl Terminal {
         lines
     }
 
-    pub fn focus_in(&self) {
-        if self.last_content.mode.contains(TermMode::FOCUS_IN_OUT) {
-            self.write_to_pty("\x1b[I".to_string());
-        }
+    /// The whole buffer as text, including the scrollback. Soft-wrapped lines are
+    /// joined, trailing blank cells are trimmed and wide characters appear once.
+    pub fn buffer_to_string(&self) -> String {
+        let term = self.term.clone();
+        let terminal = term.lock_unfair();
+        buffer_text(&terminal)
+    }
+
+    /// How long the cursor stays in each state while blinking, from the settings.
+    pub fn blink_interval(&self, cx: &AppContext) -> Duration {
+        let interval_ms = TerminalSettings::get_global(cx).blink_interval_ms;
+        Duration::from_millis(interval_ms.max(MIN_BLINK_INTERVAL_MS))
+    }
+
+    /// Applies a change of the blinking setting to the running terminal.
+    pub fn set_blink(&mut self, blink: TerminalBlink) {
+        self.events.push_back(InternalEvent::SetBlink(blink));
+    }
+
+    pub fn focus_in(&mut self) {
+        self.events.push_back(InternalEvent::FocusReport(true));
     }
 
     pub fn focus_out(&mut self) {
         self.last_mouse_position = None;
-        if self.last_content.mode.contains(TermMode::FOCUS_IN_OUT) {
-            self.write_to_pty("\x1b[O".to_string());
-        }
+        self.events.push_back(InternalEvent::FocusReport(false));
     }
 
     pub fn mouse_changed(&mut self, point: AlacPoint, side: AlacDirection) -> bool {
@@ -1214,7 +2167,12 @@ impl Terminal {
     }
 
     pub fn mouse_mode(&self, shift: bool) -> bool {
-        self.last_content.mode.intersects(TermMode::MOUSE_MODE) && !shift
+        self.wants_mouse() && !shift
+    }
+
+    /// Whether the running program has asked for mouse reports, whatever the modifiers.
+    pub fn wants_mouse(&self) -> bool {
+        self.last_content.mode.intersects(TermMode::MOUSE_MODE)
     }
 
     pub fn mouse_move(&mut self, e: &MouseMoveEvent, origin: Point<Pixels>) {
@@ -1232,8 +2190,15 @@ impl Terminal {
                     self.pty_tx.notify(bytes);
                 }
             }
-        } else if self.secondary_pressed {
-            self.word_from_position(Some(position));
+        } else {
+            self.set_hovered_point(Some(grid_point(
+                position,
+                self.last_content.size,
+                self.last_content.display_offset,
+            )));
+            if self.secondary_pressed {
+                self.word_from_position(Some(position));
+            }
         }
     }
 
@@ -1263,13 +2228,12 @@ impl Terminal {
                 .push_back(InternalEvent::UpdateSelection(position));
 
             // Doesn't make sense to scroll the alt screen
-            if !self.last_content.mode.contains(TermMode::ALT_SCREEN) {
-                let scroll_delta = match self.drag_line_delta(e, region) {
-                    Some(value) => value,
-                    None => return,
-                };
-
-                let scroll_lines = (scroll_delta / self.last_content.size.line_height) as i32;
+            if !self.is_alternate_screen() {
+                let scroll_lines =
+                    match drag_line_delta(e.position, region, self.last_content.size.line_height) {
+                        Some(value) => value,
+                        None => return,
+                    };
 
                 self.events
                     .push_back(InternalEvent::Scroll(AlacScroll::Delta(scroll_lines)));
@@ -1277,20 +2241,6 @@ impl Terminal {
         }
     }
 
-    fn drag_line_delta(&mut self, e: &MouseMoveEvent, region: Bounds<Pixels>) -> Option<Pixels> {
-        //TODO: Why do these need to be doubled? Probably the same problem that the IME has
-        let top = region.origin.y + (self.last_content.size.line_height * 2.);
-        let bottom = region.lower_left().y - (self.last_content.size.line_height * 2.);
-        let scroll_delta = if e.position.y < top {
-            (top - e.position.y).pow(1.1)
-        } else if e.position.y > bottom {
-            -((e.position.y - bottom).pow(1.1))
-        } else {
-            return None; //Nothing to do
-        };
-        Some(scroll_delta)
-    }
-
     pub fn mouse_down(
         &mut self,
         e: &MouseDownEvent,
@@ -1319,9 +2269,12 @@ impl Terminal {
                         self.last_content.size,
                         self.last_content.display_offset,
                     );
+                    let (point, side) = wide_char_point_and_side(point, side, &self.last_content);
 
                     let selection_type = match e.click_count {
                         0 => return, //This is a release
+                        // Holding alt selects a rectangular block, e.g. a column of a table
+                        1 if e.modifiers.alt => Some(SelectionType::Block),
                         1 => Some(SelectionType::Simple),
                         2 => Some(SelectionType::Semantic),
                         3 => Some(SelectionType::Lines),
@@ -1408,6 +2361,17 @@ impl Terminal {
                         self.pty_tx.notify(scroll);
                     }
                 };
+
+                // Only programs capturing the mouse can act on horizontal scrolling
+                if let Some(scroll_columns) = self.determine_scroll_columns(e) {
+                    if let Some(scrolls) =
+                        horizontal_scroll_report(point, scroll_columns, e, self.last_content.mode)
+                    {
+                        for scroll in scrolls {
+                            self.pty_tx.notify(scroll);
+                        }
+                    }
+                }
             } else if self
                 .last_content
                 .mode
@@ -1430,29 +2394,66 @@ impl Terminal {
     }
 
     fn determine_scroll_lines(&mut self, e: &ScrollWheelEvent, mouse_mode: bool) -> Option<i32> {
-        let scroll_multiplier = if mouse_mode { 1. } else { SCROLL_MULTIPLIER };
+        let scroll_multiplier = if mouse_mode {
+            1.
+        } else {
+            self.scroll_multiplier
+        };
         let line_height = self.last_content.size.line_height;
         match e.touch_phase {
             /* Reset scroll state on started */
             TouchPhase::Started => {
                 self.scroll_px = px(0.);
+                self.horizontal_scroll_px = px(0.);
                 None
             }
             /* Calculate the appropriate scroll lines */
-            TouchPhase::Moved => {
-                let old_offset = (self.scroll_px / line_height) as i32;
-
-                self.scroll_px += e.delta.pixel_delta(line_height).y * scroll_multiplier;
+            TouchPhase::Moved => Some(accumulate_scroll(
+                &mut self.scroll_px,
+                e.delta.pixel_delta(line_height).y * scroll_multiplier,
+                line_height,
+            )),
+            /* Drop the sub-line remainder so the content doesn't stay shifted */
+            TouchPhase::Ended => {
+                self.scroll_px = px(0.);
+                None
+            }
+        }
+    }
 
-                let new_offset = (self.scroll_px / line_height) as i32;
+    /// The sub-line remainder of the accumulated scroll, in pixels, by which the
+    /// rendered content can be shifted down for smooth scrolling. This is zero
+    /// whenever the viewport can't move in that direction.
+    pub fn scroll_pixel_offset(&self) -> f32 {
+        if self
+            .last_content
+            .mode
+            .intersects(TermMode::MOUSE_MODE | TermMode::ALT_SCREEN)
+        {
+            return 0.;
+        }
 
-                // Whenever we hit the edges, reset our stored scroll to 0
-                // so we can respond to changes in direction quickly
-                self.scroll_px %= self.last_content.size.height();
+        let offset = f32::from(self.scroll_px % self.last_content.size.line_height);
+        let display_offset = self.last_content.display_offset;
+        if (offset < 0. && display_offset == 0)
+            || (offset > 0. && display_offset >= self.term.lock_unfair().history_size())
+        {
+            0.
+        } else {
+            offset
+        }
+    }
 
-                Some(new_offset - old_offset)
-            }
-            TouchPhase::Ended => None,
+    fn determine_scroll_columns(&mut self, e: &ScrollWheelEvent) -> Option<i32> {
+        let cell_width = self.last_content.size.cell_width;
+        match e.touch_phase {
+            TouchPhase::Moved => Some(accumulate_scroll(
+                &mut self.horizontal_scroll_px,
+                e.delta.pixel_delta(self.last_content.size.line_height).x,
+                cell_width,
+            )),
+            // The accumulator is reset along with the vertical one
+            TouchPhase::Started | TouchPhase::Ended => None,
         }
     }
 
@@ -1462,10 +2463,94 @@ impl Terminal {
         cx: &mut ModelContext<Self>,
     ) -> Task<Vec<RangeInclusive<AlacPoint>>> {
         let term = self.term.clone();
+        let max_search_lines = max_search_lines(cx);
         cx.background_executor().spawn(async move {
             let term = term.lock();
 
-            all_search_matches(&term, &mut searcher).collect()
+            if max_search_lines == 0 {
+                all_search_matches(&term, &mut searcher).collect()
+            } else {
+                viewport_search_matches(&term, &mut searcher, max_search_lines).collect()
+            }
+        })
+    }
+
+    /// Like `find_matches`, for a literal query whose regex is `pattern`. When the
+    /// pattern extends the one of the previous call and the content hasn't changed
+    /// since, only the previous matches are re-checked instead of the whole buffer.
+    pub fn find_literal_matches(
+        &mut self,
+        pattern: String,
+        mut searcher: RegexSearch,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Vec<RangeInclusive<AlacPoint>>> {
+        let generation = self.content_generation;
+        // Limited searches depend on the scroll position, which can change between calls.
+        let searches_all = max_search_lines(cx) == 0;
+        let previous_matches = self
+            .literal_search
+            .as_ref()
+            .filter(|search| {
+                searches_all
+                    && search.generation == generation
+                    && pattern.starts_with(&search.pattern)
+            })
+            .map(|search| search.matches.clone());
+
+        let search = match previous_matches {
+            Some(previous_matches) => {
+                let term = self.term.clone();
+                let max_match_cells = pattern.len() * 2;
+                cx.background_executor().spawn(async move {
+                    let term = term.lock();
+                    refine_search_matches(&term, &mut searcher, previous_matches, max_match_cells)
+                })
+            }
+            None => self.find_matches(searcher, cx),
+        };
+
+        cx.spawn(|terminal, mut cx| async move {
+            let matches = search.await;
+            terminal
+                .update(&mut cx, |terminal, _| {
+                    terminal.literal_search = Some(LiteralSearch {
+                        pattern,
+                        generation,
+                        matches: matches.clone(),
+                    });
+                })
+                .ok();
+            matches
+        })
+    }
+
+    /// Finds the first match in `direction` from the current selection, or from the
+    /// edge of the viewport when nothing is selected, wrapping around the buffer.
+    /// Unlike `find_matches`, only the buffer up to that match is searched. The match
+    /// is selected and scrolled into view.
+    pub fn find_next(
+        &mut self,
+        mut searcher: RegexSearch,
+        direction: AlacDirection,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Option<RangeInclusive<AlacPoint>>> {
+        let term = self.term.clone();
+        let search = cx.background_executor().spawn(async move {
+            let term = term.lock();
+            next_search_match(&term, &mut searcher, direction)
+        });
+
+        cx.spawn(|terminal, mut cx| async move {
+            let search_match = search.await?;
+            terminal
+                .update(&mut cx, |terminal, _| {
+                    terminal.selected_match = None;
+                    terminal
+                        .set_selection(Some((make_selection(&search_match), *search_match.end())));
+                    terminal.scroll_match_into_view(&search_match, ScrollAlignment::Center);
+                })
+                .ok()?;
+            Some(search_match)
         })
     }
 
@@ -1476,51 +2561,153 @@ impl Terminal {
             .map(|process| process.cwd.clone())
     }
 
+    /// The name of the detected foreground process, e.g. `vim`. Unlike
+    /// `breadcrumb_text`, this doesn't rely on the program setting a title.
+    pub fn foreground_process_name(&self) -> Option<String> {
+        self.pty_info
+            .current
+            .as_ref()
+            .map(|process| process.name.clone())
+    }
+
+    /// The pid of the process spawned in the terminal, usually the shell.
+    pub fn shell_pid(&self) -> u32 {
+        self.pty_info.shell_pid()
+    }
+
+    /// The pid of the process in the foreground, like `vim` started from the shell.
+    pub fn foreground_pid(&self) -> Option<u32> {
+        self.pty_info.foreground_pid()
+    }
+
+    /// Whether a program other than the shell is in the foreground, like a build or an
+    /// editor started from it. Not to be confused with the Zed task, see `task`.
+    pub fn has_running_task(&self) -> bool {
+        self.foreground_pid()
+            .is_some_and(|pid| pid != self.shell_pid())
+    }
+
+    /// Whether closing the terminal loses nothing but the shell, so the workspace
+    /// can ask before killing a program still running in it.
+    pub fn can_close_without_confirmation(&self) -> bool {
+        !self.has_running_task()
+    }
+
+    /// Sends the signal to the foreground process group, for programs that swallow
+    /// the keys that would normally interrupt them.
+    #[cfg(unix)]
+    pub fn send_signal(&self, signal: libc::c_int) -> std::io::Result<()> {
+        self.pty_info.send_signal(signal)
+    }
+
+    #[cfg(unix)]
+    pub fn interrupt(&self) -> std::io::Result<()> {
+        self.send_signal(libc::SIGINT)
+    }
+
+    #[cfg(unix)]
+    pub fn terminate(&self) -> std::io::Result<()> {
+        self.send_signal(libc::SIGTERM)
+    }
+
+    #[cfg(unix)]
+    pub fn kill(&self) -> std::io::Result<()> {
+        self.send_signal(libc::SIGKILL)
+    }
+
     pub fn title(&self, truncate: bool) -> String {
-        const MAX_CHARS: usize = 25;
         match &self.task {
             Some(task_state) => {
                 if truncate {
-                    truncate_and_trailoff(&task_state.label, MAX_CHARS)
+                    truncate_and_trailoff(&task_state.label, MAX_TITLE_CHARS)
                 } else {
                     task_state.full_label.clone()
                 }
             }
-            None => self
-                .pty_info
-                .current
-                .as_ref()
-                .map(|fpi| {
-                    let process_file = fpi
-                        .cwd
-                        .file_name()
-                        .map(|name| name.to_string_lossy().to_string())
-                        .unwrap_or_default();
-
-                    let argv = fpi.argv.clone();
-                    let process_name = format!(
-                        "{}{}",
-                        fpi.name,
-                        if argv.len() >= 1 {
-                            format!(" {}", (argv[1..]).join(" "))
-                        } else {
-                            "".to_string()
-                        }
-                    );
-                    let (process_file, process_name) = if truncate {
-                        (
-                            truncate_and_trailoff(&process_file, MAX_CHARS),
-                            truncate_and_trailoff(&process_name, MAX_CHARS),
-                        )
-                    } else {
-                        (process_file, process_name)
-                    };
-                    format!("{process_file} — {process_name}")
-                })
-                .unwrap_or_else(|| "Terminal".to_string()),
+            None => {
+                let title = match self.title_source {
+                    _ if self.title_template.is_some() => {
+                        Some(self.formatted_title()).filter(|title| !title.trim().is_empty())
+                    }
+                    TitleSource::Process => None,
+                    TitleSource::Application => Some(self.breadcrumb_text.trim())
+                        .filter(|title| !title.is_empty())
+                        .map(|title| title.to_string()),
+                    TitleSource::WorkingDirectory => self.working_directory().and_then(|cwd| {
+                        cwd.file_name()
+                            .map(|name| name.to_string_lossy().to_string())
+                    }),
+                };
+                match title {
+                    Some(title) if truncate => truncate_and_trailoff(&title, MAX_TITLE_CHARS),
+                    Some(title) => title,
+                    None => self.process_title(truncate),
+                }
+            }
         }
     }
 
+    /// The title from the `title_template` setting, with `{cwd}`, `{cwd_basename}`,
+    /// `{process}` and `{title}` replaced, or the untruncated `title` without one.
+    pub fn formatted_title(&self) -> String {
+        let Some(template) = &self.title_template else {
+            return self.title(false);
+        };
+        let cwd = self.working_directory();
+        let cwd_basename = cwd
+            .as_ref()
+            .and_then(|cwd| cwd.file_name())
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let cwd = cwd
+            .map(|cwd| cwd.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let process = self.foreground_process_name().unwrap_or_default();
+        fill_title_template(
+            template,
+            &[
+                ("cwd", &cwd),
+                ("cwd_basename", &cwd_basename),
+                ("process", &process),
+                ("title", self.breadcrumb_text.trim()),
+            ],
+        )
+    }
+
+    fn process_title(&self, truncate: bool) -> String {
+        self.pty_info
+            .current
+            .as_ref()
+            .map(|fpi| {
+                let process_file = fpi
+                    .cwd
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default();
+
+                let argv = fpi.argv.clone();
+                let process_name = format!(
+                    "{}{}",
+                    fpi.name,
+                    if argv.len() >= 1 {
+                        format!(" {}", (argv[1..]).join(" "))
+                    } else {
+                        "".to_string()
+                    }
+                );
+                let (process_file, process_name) = if truncate {
+                    (
+                        truncate_and_trailoff(&process_file, MAX_TITLE_CHARS),
+                        truncate_and_trailoff(&process_name, MAX_TITLE_CHARS),
+                    )
+                } else {
+                    (process_file, process_name)
+                };
+                format!("{process_file} — {process_name}")
+            })
+            .unwrap_or_else(|| "Terminal".to_string())
+    }
+
     pub fn can_navigate_to_selected_word(&self) -> bool {
         self.secondary_pressed && self.hovered_word
     }
@@ -1550,8 +2737,13 @@ impl Terminal {
         let task = match &mut self.task {
             Some(task) => task,
             None => {
-                if error_code.is_none() {
-                    cx.emit(Event::CloseTerminal);
+                let keep_open = match self.keep_open_on_exit {
+                    KeepOpenOnExit::Never => false,
+                    KeepOpenOnExit::OnFailure => self.exit_status.is_some_and(|status| status != 0),
+                    KeepOpenOnExit::Always => true,
+                };
+                if error_code.is_none() && !keep_open {
+                    self.emit(Event::CloseTerminal, cx);
                 }
                 return;
             }
@@ -1577,11 +2769,11 @@ impl Terminal {
         match task.hide {
             HideStrategy::Never => {}
             HideStrategy::Always => {
-                cx.emit(Event::CloseTerminal);
+                self.emit(Event::CloseTerminal, cx);
             }
             HideStrategy::OnSuccess => {
                 if finished_successfully {
-                    cx.emit(Event::CloseTerminal);
+                    self.emit(Event::CloseTerminal, cx);
                 }
             }
         }
@@ -1652,6 +2844,39 @@ impl Drop for Terminal {
 
 impl EventEmitter<Event> for Terminal {}
 
+/// The whole URL at `point`, from an OSC 8 hyperlink or else a URL regex match.
+fn url_at<T>(term: &Term<T>, point: AlacPoint, url_regex: &mut RegexSearch) -> Option<String> {
+    let point = point.grid_clamp(term, Boundary::Grid);
+    if let Some(link) = term.grid().index(point).hyperlink() {
+        return Some(link.uri().to_owned());
+    }
+    let url_match = regex_match_at(term, point, url_regex)?;
+    Some(term.bounds_to_string(*url_match.start(), *url_match.end()))
+}
+
+fn word_at<T>(term: &Term<T>, point: AlacPoint) -> Option<(String, RangeInclusive<AlacPoint>)> {
+    let point = point.grid_clamp(term, Boundary::Grid);
+    let start = term.semantic_search_left(point);
+    let end = term.semantic_search_right(point);
+    let word = term.bounds_to_string(start, end);
+    let escape_chars = term.semantic_escape_chars();
+    if word.trim().is_empty() || word.chars().any(|c| escape_chars.contains(c)) {
+        return None;
+    }
+    Some((word, start..=end))
+}
+
+fn path_at<T>(
+    term: &Term<T>,
+    point: AlacPoint,
+    path_regex: &mut RegexSearch,
+) -> Option<PathWithPosition> {
+    let point = point.grid_clamp(term, Boundary::Grid);
+    let path_match = regex_match_at(term, point, path_regex)?;
+    let text = term.bounds_to_string(*path_match.start(), *path_match.end());
+    Some(PathWithPosition::parse_str(&text))
+}
+
 /// Based on alacritty/src/display/hint.rs > regex_match_at
 /// Retrieve the match, if the specified point is inside the content matching the regex.
 fn regex_match_at<T>(term: &Term<T>, point: AlacPoint, regex: &mut RegexSearch) -> Option<Match> {
@@ -1676,85 +2901,543 @@ pub fn visible_regex_match_iter<'a, T>(
         .take_while(move |rm| rm.start().line <= viewport_end)
 }
 
-fn make_selection(range: &RangeInclusive<AlacPoint>) -> Selection {
-    let mut selection = Selection::new(SelectionType::Simple, *range.start(), AlacDirection::Left);
-    selection.update(*range.end(), AlacDirection::Right);
-    selection
+/// Replaces the `{name}` tokens of a title template, in a single pass so values
+/// containing braces are kept as they are. Unknown tokens are left untouched.
+fn fill_title_template(template: &str, tokens: &[(&str, &str)]) -> String {
+    let mut title = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        title.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let token = tokens.iter().find(|(name, _)| {
+            rest[1..]
+                .strip_prefix(name)
+                .is_some_and(|after| after.starts_with('}'))
+        });
+        match token {
+            Some((name, value)) => {
+                title.push_str(value);
+                rest = &rest[name.len() + 2..];
+            }
+            None => {
+                title.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    title.push_str(rest);
+    title
 }
 
-fn all_search_matches<'a, T>(
-    term: &'a Term<T>,
-    regex: &'a mut RegexSearch,
-) -> impl Iterator<Item = Match> + 'a {
-    let start = AlacPoint::new(term.grid().topmost_line(), Column(0));
-    let end = AlacPoint::new(term.grid().bottommost_line(), term.grid().last_column());
-    RegexIter::new(start, end, AlacDirection::Right, term, regex)
+fn buffer_text<T>(term: &Term<T>) -> String {
+    let start = AlacPoint::new(term.topmost_line(), Column(0));
+    let end = AlacPoint::new(term.bottommost_line(), term.last_column());
+    let mut text = term.bounds_to_string(start, end);
+    text.truncate(text.trim_end().len());
+    text
 }
 
-fn content_index_for_mouse(pos: Point<Pixels>, size: &TerminalSize) -> usize {
-    let col = (pos.x / size.cell_width()).round() as usize;
-    let clamped_col = min(col, size.columns() - 1);
-    let row = (pos.y / size.line_height()).round() as usize;
-    let clamped_row = min(row, size.screen_lines() - 1);
-    clamped_row * size.columns() + clamped_col
+/// The cell attributes kept by formatted copies.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct CopyStyle {
+    fg: AnsiColor,
+    bg: AnsiColor,
+    flags: Flags,
 }
 
-/// Converts an 8 bit ANSI color to its GPUI equivalent.
-/// Accepts `usize` for compatibility with the `alacritty::Colors` interface,
-/// Other than that use case, should only be called with values in the [0,255] range
-pub fn get_color_at_index(index: usize, theme: &Theme) -> Hsla {
-    let colors = theme.colors();
-
-    match index {
-        // 0-15 are the same as the named colors above
-        0 => colors.terminal_ansi_black,
-        1 => colors.terminal_ansi_red,
-        2 => colors.terminal_ansi_green,
-        3 => colors.terminal_ansi_yellow,
-        4 => colors.terminal_ansi_blue,
-        5 => colors.terminal_ansi_magenta,
-        6 => colors.terminal_ansi_cyan,
-        7 => colors.terminal_ansi_white,
-        8 => colors.terminal_ansi_bright_black,
-        9 => colors.terminal_ansi_bright_red,
-        10 => colors.terminal_ansi_bright_green,
-        11 => colors.terminal_ansi_bright_yellow,
-        12 => colors.terminal_ansi_bright_blue,
-        13 => colors.terminal_ansi_bright_magenta,
-        14 => colors.terminal_ansi_bright_cyan,
-        15 => colors.terminal_ansi_bright_white,
-        // 16-231 are mapped to their RGB colors on a 0-5 range per channel
-        16..=231 => {
-            let (r, g, b) = rgb_for_index(index as u8); // Split the index into its ANSI-RGB components
-            let step = (u8::MAX as f32 / 5.).floor() as u8; // Split the RGB range into 5 chunks, with floor so no overflow
-            rgba_color(r * step, g * step, b * step) // Map the ANSI-RGB components to an RGB color
-        }
-        // 232-255 are a 24 step grayscale from black to white
-        232..=255 => {
-            let i = index as u8 - 232; // Align index to 0..24
-            let step = (u8::MAX as f32 / 24.).floor() as u8; // Split the RGB grayscale values into 24 chunks
-            rgba_color(i * step, i * step, i * step) // Map the ANSI-grayscale components to the RGB-grayscale
+impl Default for CopyStyle {
+    fn default() -> Self {
+        Self {
+            fg: AnsiColor::Named(NamedColor::Foreground),
+            bg: AnsiColor::Named(NamedColor::Background),
+            flags: Flags::empty(),
         }
-        // For compatibility with the alacritty::Colors interface
-        256 => colors.text,
-        257 => colors.background,
-        258 => theme.players().local().cursor,
-        259 => colors.terminal_ansi_dim_black,
-        260 => colors.terminal_ansi_dim_red,
-        261 => colors.terminal_ansi_dim_green,
-        262 => colors.terminal_ansi_dim_yellow,
-        263 => colors.terminal_ansi_dim_blue,
-        264 => colors.terminal_ansi_dim_magenta,
-        265 => colors.terminal_ansi_dim_cyan,
-        266 => colors.terminal_ansi_dim_white,
-        267 => colors.terminal_bright_foreground,
-        268 => colors.terminal_ansi_black, // 'Dim Background', non-standard color
-
-        _ => black(),
     }
 }
 
-/// Generates the RGB channels in [0, 5] for a given index into the 6x6x6 ANSI color cube.
+impl CopyStyle {
+    fn of(cell: &Cell) -> Self {
+        let flags = Flags::BOLD
+            | Flags::DIM
+            | Flags::ITALIC
+            | Flags::ALL_UNDERLINES
+            | Flags::STRIKEOUT
+            | Flags::INVERSE;
+        Self {
+            fg: cell.fg,
+            bg: cell.bg,
+            flags: cell.flags & flags,
+        }
+    }
+}
+
+/// The selected text in the given format, dropping trailing blanks on each line and
+/// joining wrapped lines like plain copies do.
+fn formatted_selection<T>(term: &Term<T>, format: CopyFormat, theme: &Theme) -> Option<String> {
+    let range = match format {
+        CopyFormat::Plain => return term.selection_to_string(),
+        CopyFormat::Ansi | CopyFormat::Html => term.selection.as_ref()?.to_range(term)?,
+    };
+
+    let mut output = String::new();
+    if format == CopyFormat::Html {
+        output.push_str("<pre>");
+    }
+    let last_column = term.last_column();
+    for line in range.start.line.0..=range.end.line.0 {
+        let line = Line(line);
+        let row = &term.grid()[line];
+        let (start, end) = if range.is_block {
+            (range.start.column, range.end.column)
+        } else {
+            (
+                if line == range.start.line {
+                    range.start.column
+                } else {
+                    Column(0)
+                },
+                if line == range.end.line {
+                    range.end.column
+                } else {
+                    last_column
+                },
+            )
+        };
+        let cells = (start.0..=end.0)
+            .map(|column| &row[Column(column)])
+            .filter(|cell| {
+                !cell
+                    .flags
+                    .intersects(Flags::WIDE_CHAR_SPACER | Flags::LEADING_WIDE_CHAR_SPACER)
+            })
+            .collect::<Vec<_>>();
+        let len = cells
+            .iter()
+            .rposition(|cell| cell.c != ' ' || CopyStyle::of(cell) != CopyStyle::default())
+            .map_or(0, |last| last + 1);
+
+        let mut style = CopyStyle::default();
+        for cell in &cells[..len] {
+            let cell_style = CopyStyle::of(cell);
+            if cell_style != style {
+                close_copy_style(&mut output, format, style);
+                open_copy_style(&mut output, format, cell_style, term, theme);
+                style = cell_style;
+            }
+            for c in iter::once(cell.c).chain(cell.zerowidth().into_iter().flatten().copied()) {
+                match (format, c) {
+                    (CopyFormat::Html, '&') => output.push_str("&amp;"),
+                    (CopyFormat::Html, '<') => output.push_str("&lt;"),
+                    (CopyFormat::Html, '>') => output.push_str("&gt;"),
+                    (CopyFormat::Html, '"') => output.push_str("&quot;"),
+                    _ => output.push(c),
+                }
+            }
+        }
+        close_copy_style(&mut output, format, style);
+
+        let wrapped = !range.is_block && row[last_column].flags.contains(Flags::WRAPLINE);
+        if line != range.end.line && !wrapped {
+            output.push('\n');
+        }
+    }
+    if format == CopyFormat::Html {
+        output.push_str("</pre>");
+    }
+    Some(output)
+}
+
+fn open_copy_style<T>(
+    output: &mut String,
+    format: CopyFormat,
+    style: CopyStyle,
+    term: &Term<T>,
+    theme: &Theme,
+) {
+    if style == CopyStyle::default() {
+        return;
+    }
+    let flags = style.flags;
+    if format == CopyFormat::Html {
+        let (mut fg, mut bg) = (
+            (style.fg != CopyStyle::default().fg).then_some(style.fg),
+            (style.bg != CopyStyle::default().bg).then_some(style.bg),
+        );
+        if flags.contains(Flags::INVERSE) {
+            (fg, bg) = (Some(style.bg), Some(style.fg));
+        }
+        let mut declarations = Vec::new();
+        for (property, color) in [("color", fg), ("background-color", bg)] {
+            if let Some(color) = color {
+                let Rgb { r, g, b } = resolve_copy_color(color, term, theme);
+                declarations.push(format!("{property}:#{r:02x}{g:02x}{b:02x}"));
+            }
+        }
+        if flags.contains(Flags::BOLD) {
+            declarations.push("font-weight:bold".into());
+        }
+        if flags.contains(Flags::DIM) {
+            declarations.push("opacity:0.7".into());
+        }
+        if flags.contains(Flags::ITALIC) {
+            declarations.push("font-style:italic".into());
+        }
+        let mut decoration = Vec::new();
+        if flags.intersects(Flags::ALL_UNDERLINES) {
+            decoration.push("underline");
+        }
+        if flags.contains(Flags::STRIKEOUT) {
+            decoration.push("line-through");
+        }
+        if flags.contains(Flags::DOUBLE_UNDERLINE) {
+            decoration.push("double");
+        } else if flags.contains(Flags::UNDERCURL) {
+            decoration.push("wavy");
+        } else if flags.contains(Flags::DOTTED_UNDERLINE) {
+            decoration.push("dotted");
+        } else if flags.contains(Flags::DASHED_UNDERLINE) {
+            decoration.push("dashed");
+        }
+        if !decoration.is_empty() {
+            declarations.push(format!("text-decoration:{}", decoration.join(" ")));
+        }
+        output.push_str(&format!("<span style=\"{}\">", declarations.join(";")));
+    } else {
+        let mut params = Vec::new();
+        for (flag, param) in [
+            (Flags::BOLD, "1"),
+            (Flags::DIM, "2"),
+            (Flags::ITALIC, "3"),
+            (Flags::UNDERLINE, "4"),
+            (Flags::DOUBLE_UNDERLINE, "4:2"),
+            (Flags::UNDERCURL, "4:3"),
+            (Flags::DOTTED_UNDERLINE, "4:4"),
+            (Flags::DASHED_UNDERLINE, "4:5"),
+            (Flags::INVERSE, "7"),
+            (Flags::STRIKEOUT, "9"),
+        ] {
+            if flags.contains(flag) {
+                params.push(param.to_string());
+            }
+        }
+        if style.fg != CopyStyle::default().fg {
+            params.push(sgr_color(style.fg, 30, 90, 38, term, theme));
+        }
+        if style.bg != CopyStyle::default().bg {
+            params.push(sgr_color(style.bg, 40, 100, 48, term, theme));
+        }
+        output.push_str(&format!("\x1b[{}m", params.join(";")));
+    }
+}
+
+fn close_copy_style(output: &mut String, format: CopyFormat, style: CopyStyle) {
+    if style == CopyStyle::default() {
+        return;
+    }
+    match format {
+        CopyFormat::Plain => {}
+        CopyFormat::Ansi => output.push_str("\x1b[0m"),
+        CopyFormat::Html => output.push_str("</span>"),
+    }
+}
+
+/// The SGR parameters selecting a color, with the 16 ANSI colors kept as named
+/// ones so they follow the palette of wherever they're pasted.
+fn sgr_color<T>(
+    color: AnsiColor,
+    base: u8,
+    bright_base: u8,
+    extended: u8,
+    term: &Term<T>,
+    theme: &Theme,
+) -> String {
+    match color {
+        AnsiColor::Named(named) if (named as usize) < 8 => format!("{}", base + named as u8),
+        AnsiColor::Named(named) if (named as usize) < 16 => {
+            format!("{}", bright_base + named as u8 - 8)
+        }
+        AnsiColor::Indexed(index) => format!("{extended};5;{index}"),
+        color => {
+            let Rgb { r, g, b } = resolve_copy_color(color, term, theme);
+            format!("{extended};2;{r};{g};{b}")
+        }
+    }
+}
+
+fn resolve_copy_color<T>(color: AnsiColor, term: &Term<T>, theme: &Theme) -> Rgb {
+    let index = match color {
+        AnsiColor::Spec(rgb) => return rgb,
+        AnsiColor::Named(named) => named as usize,
+        AnsiColor::Indexed(index) => index as usize,
+    };
+    term.colors()[index].unwrap_or_else(|| to_alac_rgb(get_color_at_index(index, theme)))
+}
+
+fn visible_regex_matches_with_text<T>(
+    term: &Term<T>,
+    regex: &mut RegexSearch,
+) -> Vec<(RangeInclusive<AlacPoint>, String)> {
+    visible_regex_match_iter(term, regex)
+        .map(|regex_match| {
+            let text = term.bounds_to_string(*regex_match.start(), *regex_match.end());
+            (regex_match, text)
+        })
+        .collect()
+}
+
+/// Overwrites the cells of the previous sync in place, returning the rows whose
+/// cells changed, or all of them when the cell count did. Scrolling moves every
+/// row, so cells are compared by grid point too.
+fn update_cells(
+    cells: &mut Vec<IndexedCell>,
+    new_cells: impl IntoIterator<Item = IndexedCell>,
+    columns: usize,
+) -> Vec<usize> {
+    let columns = columns.max(1);
+    let previous_len = cells.len();
+    let mut dirty_lines = Vec::new();
+    let mut len = 0;
+    for cell in new_cells {
+        let changed = match cells.get_mut(len) {
+            Some(previous) => {
+                let changed = previous.point != cell.point || previous.cell != cell.cell;
+                *previous = cell;
+                changed
+            }
+            None => {
+                cells.push(cell);
+                true
+            }
+        };
+        let line = len / columns;
+        if changed && dirty_lines.last() != Some(&line) {
+            dirty_lines.push(line);
+        }
+        len += 1;
+    }
+    cells.truncate(len);
+    if len != previous_len {
+        return (0..len.div_ceil(columns)).collect();
+    }
+    dirty_lines
+}
+
+/// Groups adjacent cells sharing the same OSC 8 hyperlink into a single range.
+fn hyperlinks_in_cells(cells: &[IndexedCell]) -> Vec<(RangeInclusive<AlacPoint>, String)> {
+    let mut hyperlinks = Vec::new();
+    let mut current: Option<(AlacPoint, AlacPoint, Hyperlink)> = None;
+    for cell in cells {
+        let link = cell.hyperlink();
+        if let (Some((_, end, current_link)), Some(link)) = (current.as_mut(), link.as_ref()) {
+            if current_link == link {
+                *end = cell.point;
+                continue;
+            }
+        }
+
+        if let Some((start, end, link)) = current.take() {
+            hyperlinks.push((start..=end, link.uri().to_owned()));
+        }
+        current = link.map(|link| (cell.point, cell.point, link));
+    }
+    if let Some((start, end, link)) = current {
+        hyperlinks.push((start..=end, link.uri().to_owned()));
+    }
+    hyperlinks
+}
+
+fn make_selection(range: &RangeInclusive<AlacPoint>) -> Selection {
+    let mut selection = Selection::new(SelectionType::Simple, *range.start(), AlacDirection::Left);
+    selection.update(*range.end(), AlacDirection::Right);
+    selection
+}
+
+fn all_search_matches<'a, T>(
+    term: &'a Term<T>,
+    regex: &'a mut RegexSearch,
+) -> impl Iterator<Item = Match> + 'a {
+    let start = AlacPoint::new(term.grid().topmost_line(), Column(0));
+    let end = AlacPoint::new(term.grid().bottommost_line(), term.grid().last_column());
+    RegexIter::new(start, end, AlacDirection::Right, term, regex)
+}
+
+/// Re-runs `regex` over the previous matches of a literal query it extends. Every
+/// occurrence of the extended query starts within one of the previous matches, not
+/// necessarily at its start when the query overlaps itself, e.g. "aab" in "aaab"
+/// after "aa" matched at the first column.
+fn refine_search_matches<T>(
+    term: &Term<T>,
+    regex: &mut RegexSearch,
+    matches: Vec<Match>,
+    max_match_cells: usize,
+) -> Vec<Match> {
+    let mut refined = Vec::new();
+    let mut resume_at: Option<AlacPoint> = None;
+    for search_match in matches {
+        let mut start = resume_at.map_or(*search_match.start(), |resume_at| {
+            resume_at.max(*search_match.start())
+        });
+        let last_start = *search_match.end();
+        let end = last_start.add(term, Boundary::Grid, max_match_cells);
+        while start <= last_start {
+            let Some(new_match) = term
+                .regex_search_right(regex, start, end)
+                .filter(|new_match| *new_match.start() <= last_start)
+            else {
+                break;
+            };
+
+            let next = new_match.end().add(term, Boundary::Grid, 1);
+            let at_grid_end = next <= *new_match.end();
+            refined.push(new_match);
+            resume_at = Some(next);
+            if at_grid_end {
+                return refined;
+            }
+            start = next;
+        }
+    }
+    refined
+}
+
+/// The first match in `direction` past the selection, or from the edge of the
+/// viewport when nothing is selected.
+fn next_search_match<T>(
+    term: &Term<T>,
+    regex: &mut RegexSearch,
+    direction: AlacDirection,
+) -> Option<Match> {
+    let selection = term
+        .selection
+        .as_ref()
+        .and_then(|selection| selection.to_range(term));
+    let display_offset = term.grid().display_offset() as i32;
+    let origin = match (direction, selection) {
+        (AlacDirection::Right, Some(selection)) => selection.end.add(term, Boundary::None, 1),
+        (AlacDirection::Left, Some(selection)) => selection.start.sub(term, Boundary::None, 1),
+        (AlacDirection::Right, None) => AlacPoint::new(Line(-display_offset), Column(0)),
+        (AlacDirection::Left, None) => AlacPoint::new(
+            Line(term.screen_lines() as i32 - 1 - display_offset),
+            term.last_column(),
+        ),
+    };
+    term.search_next(regex, origin, direction, Side::Left, None)
+}
+
+/// The `max_search_lines` setting, where 0 means the entire scrollback.
+fn max_search_lines(cx: &AppContext) -> usize {
+    TerminalSettings::get_global(cx)
+        .max_search_lines
+        .unwrap_or(MAX_SEARCH_LINES)
+}
+
+/// Iterate over the matches within `max_search_lines` above and below the viewport.
+fn viewport_search_matches<'a, T>(
+    term: &'a Term<T>,
+    regex: &'a mut RegexSearch,
+    max_search_lines: usize,
+) -> impl Iterator<Item = Match> + 'a {
+    let viewport_start = Line(-(term.grid().display_offset() as i32));
+    let viewport_end = viewport_start + term.bottommost_line();
+    let start_line = (viewport_start - max_search_lines).max(term.topmost_line());
+    let end_line = (viewport_end + max_search_lines).min(term.bottommost_line());
+    let start = AlacPoint::new(start_line, Column(0));
+    let end = AlacPoint::new(end_line, term.grid().last_column());
+    RegexIter::new(start, end, AlacDirection::Right, term, regex)
+}
+
+/// Moves a viewport at the bottom up a line, when there's history to show, so that
+/// alacritty holds it in place as output arrives, like any scrolled up viewport.
+fn hold_viewport<T>(term: &mut Term<T>) {
+    if term.grid().display_offset() == 0 && term.grid().history_size() > 0 {
+        term.scroll_display(AlacScroll::Delta(1));
+    }
+}
+
+/// The lines to scroll while dragging a selection near the top or bottom of `region`,
+/// faster the further out the mouse is.
+fn drag_line_delta(
+    position: Point<Pixels>,
+    region: Bounds<Pixels>,
+    line_height: Pixels,
+) -> Option<i32> {
+    //TODO: Why do these need to be doubled? Probably the same problem that the IME has
+    let top = region.origin.y + (line_height * 2.);
+    let bottom = region.lower_left().y - (line_height * 2.);
+    let scroll_delta = if position.y < top {
+        (top - position.y).pow(1.1)
+    } else if position.y > bottom {
+        -((position.y - bottom).pow(1.1))
+    } else {
+        return None; //Nothing to do
+    };
+    Some((scroll_delta / line_height) as i32)
+}
+
+fn content_index_for_mouse(pos: Point<Pixels>, size: &TerminalSize) -> usize {
+    let col = (pos.x / size.cell_width()).round() as usize;
+    let clamped_col = min(col, size.columns() - 1);
+    let row = (pos.y / size.line_height()).round() as usize;
+    let clamped_row = min(row, size.screen_lines() - 1);
+    clamped_row * size.columns() + clamped_col
+}
+
+/// Converts an 8 bit ANSI color to its GPUI equivalent.
+/// Accepts `usize` for compatibility with the `alacritty::Colors` interface,
+/// Other than that use case, should only be called with values in the [0,255] range
+pub fn get_color_at_index(index: usize, theme: &Theme) -> Hsla {
+    let colors = theme.colors();
+
+    match index {
+        // 0-15 are the same as the named colors above
+        0 => colors.terminal_ansi_black,
+        1 => colors.terminal_ansi_red,
+        2 => colors.terminal_ansi_green,
+        3 => colors.terminal_ansi_yellow,
+        4 => colors.terminal_ansi_blue,
+        5 => colors.terminal_ansi_magenta,
+        6 => colors.terminal_ansi_cyan,
+        7 => colors.terminal_ansi_white,
+        8 => colors.terminal_ansi_bright_black,
+        9 => colors.terminal_ansi_bright_red,
+        10 => colors.terminal_ansi_bright_green,
+        11 => colors.terminal_ansi_bright_yellow,
+        12 => colors.terminal_ansi_bright_blue,
+        13 => colors.terminal_ansi_bright_magenta,
+        14 => colors.terminal_ansi_bright_cyan,
+        15 => colors.terminal_ansi_bright_white,
+        // 16-231 are mapped to their RGB colors on a 0-5 range per channel
+        16..=231 => {
+            let (r, g, b) = rgb_for_index(index as u8); // Split the index into its ANSI-RGB components
+            let step = (u8::MAX as f32 / 5.).floor() as u8; // Split the RGB range into 5 chunks, with floor so no overflow
+            rgba_color(r * step, g * step, b * step) // Map the ANSI-RGB components to an RGB color
+        }
+        // 232-255 are a 24 step grayscale from black to white
+        232..=255 => {
+            let i = index as u8 - 232; // Align index to 0..24
+            let step = (u8::MAX as f32 / 24.).floor() as u8; // Split the RGB grayscale values into 24 chunks
+            rgba_color(i * step, i * step, i * step) // Map the ANSI-grayscale components to the RGB-grayscale
+        }
+        // For compatibility with the alacritty::Colors interface
+        256 => colors.text,
+        257 => colors.background,
+        258 => theme.players().local().cursor,
+        259 => colors.terminal_ansi_dim_black,
+        260 => colors.terminal_ansi_dim_red,
+        261 => colors.terminal_ansi_dim_green,
+        262 => colors.terminal_ansi_dim_yellow,
+        263 => colors.terminal_ansi_dim_blue,
+        264 => colors.terminal_ansi_dim_magenta,
+        265 => colors.terminal_ansi_dim_cyan,
+        266 => colors.terminal_ansi_dim_white,
+        267 => colors.terminal_bright_foreground,
+        268 => colors.terminal_ansi_black, // 'Dim Background', non-standard color
+
+        _ => black(),
+    }
+}
+
+/// Generates the RGB channels in [0, 5] for a given index into the 6x6x6 ANSI color cube.
 /// See: [8 bit ANSI color](https://en.wikipedia.org/wiki/ANSI_escape_code#8-bit).
 ///
 /// Wikipedia gives a formula for calculating the index for a given color:
@@ -1783,19 +3466,947 @@ pub fn rgba_color(r: u8, g: u8, b: u8) -> Hsla {
     .into()
 }
 
+/// The display offset that shows grid `line` at the given position in the viewport,
+/// clamped to the available scrollback.
+fn aligned_display_offset(
+    line: i32,
+    screen_lines: usize,
+    history_size: usize,
+    alignment: ScrollAlignment,
+) -> usize {
+    let row = match alignment {
+        ScrollAlignment::Top => 0,
+        ScrollAlignment::Center => screen_lines as i32 / 2,
+        ScrollAlignment::Bottom => screen_lines as i32 - 1,
+    };
+    (row - line).clamp(0, history_size as i32) as usize
+}
+
+///The sequence reporting a focus change, only sent to programs that enabled focus reporting
+fn focus_report(focused: bool, mode: TermMode) -> Option<&'static str> {
+    if !mode.contains(TermMode::FOCUS_IN_OUT) {
+        return None;
+    }
+    Some(if focused { "\x1b[I" } else { "\x1b[O" })
+}
+
+/// Collapses runs of consecutive events whose combined effect is all that matters:
+/// the last of several resizes or selection updates, and the sum of scroll deltas.
+/// Events are never reordered.
+fn coalesce_events(events: &mut VecDeque<InternalEvent>) {
+    let mut coalesced = VecDeque::with_capacity(events.len());
+    for event in events.drain(..) {
+        let merged = match (coalesced.back_mut(), &event) {
+            (Some(InternalEvent::Resize(last)), InternalEvent::Resize(size)) => {
+                *last = *size;
+                true
+            }
+            (
+                Some(InternalEvent::UpdateSelection(last)),
+                InternalEvent::UpdateSelection(position),
+            ) => {
+                *last = *position;
+                true
+            }
+            (
+                Some(InternalEvent::Scroll(AlacScroll::Delta(last))),
+                InternalEvent::Scroll(AlacScroll::Delta(delta)),
+            ) => {
+                *last = last.saturating_add(*delta);
+                true
+            }
+            _ => false,
+        };
+        if !merged {
+            coalesced.push_back(event);
+        }
+    }
+    *events = coalesced;
+}
+
+/// Adds `delta` to a scroll accumulator and returns how many whole steps, i.e. lines
+/// or columns, were crossed. Only the sub-step remainder is kept for the next event,
+/// so fast scrolling neither skips nor repeats steps.
+fn accumulate_scroll(scroll_px: &mut Pixels, delta: Pixels, step: Pixels) -> i32 {
+    *scroll_px += delta;
+    let steps = (*scroll_px / step) as i32;
+    *scroll_px %= step;
+    steps
+}
+
+/// Prepares text for pasting. With bracketed paste, control characters other than
+/// whitespace are dropped so that the text can't end the paste early, e.g. with an
+/// embedded `\x1b[201~`, and run as input.
+fn paste_text(text: &str, bracketed: bool) -> String {
+    if bracketed {
+        let text: String = text
+            .chars()
+            .filter(|c| !c.is_control() || matches!(c, '\t' | '\n' | '\r'))
+            .collect();
+        format!("{}{}{}", "\x1b[200~", text, "\x1b[201~")
+    } else {
+        text.replace("\r\n", "\r").replace('\n', "\r")
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use alacritty_terminal::{
-        index::{Column, Line, Point as AlacPoint},
-        term::cell::Cell,
+        event::{Event as AlacTermEvent, EventListener, VoidListener, WindowSize},
+        grid::{Dimensions, Scroll as AlacScroll},
+        index::{Column, Direction as AlacDirection, Line, Point as AlacPoint, Side},
+        selection::{Selection, SelectionType},
+        term::{
+            cell::{Cell, Flags},
+            search::RegexSearch,
+            Config, TermMode,
+        },
+        vte::ansi::{Color as AnsiColor, NamedColor, Processor, Rgb},
+        Term,
     };
-    use gpui::{point, size, Pixels};
+    use gpui::{point, px, size, Bounds, Pixels};
     use rand::{distributions::Alphanumeric, rngs::ThreadRng, thread_rng, Rng};
 
+    use collections::{HashMap, VecDeque};
+    use futures::channel::mpsc::unbounded;
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
     use crate::{
-        content_index_for_mouse, rgb_for_index, IndexedCell, TerminalContent, TerminalSize,
+        accumulate_scroll, alacritty_shell, aligned_display_offset, all_search_matches,
+        buffer_text, coalesce_events, content_index_for_mouse, drag_line_delta,
+        fill_title_template, focus_report, formatted_selection, get_color_at_index,
+        grid_point_and_side, hold_viewport, hyperlinks_in_cells, is_locale_unset, make_selection,
+        mappings::colors::to_alac_rgb, next_search_match, paste_text, path_at, pty_options,
+        quote_command, refine_search_matches, rgb_for_index, terminal_env, text_area_size_report,
+        update_cells, url_at, visible_regex_matches_with_text, wide_char_point_and_side, word_at,
+        CellUnderline, CopyFormat, IndexedCell, InternalEvent, ScrollAlignment, Shell,
+        TerminalContent, TerminalSize, ZedListener, PATH_REGEX, URL_REGEX,
     };
 
+    #[test]
+    fn test_alacritty_shell() {
+        let login_args = if cfg!(unix) {
+            vec!["-l".to_string()]
+        } else {
+            Vec::new()
+        };
+        let system_shell = || Some("/bin/zsh".to_string());
+
+        let expected_system = |args: Vec<String>| {
+            (!cfg!(target_os = "macos"))
+                .then(|| alacritty_terminal::tty::Shell::new("/bin/zsh".to_string(), args))
+        };
+        assert_eq!(
+            alacritty_shell(Shell::System, false, system_shell),
+            expected_system(Vec::new())
+        );
+        assert_eq!(
+            alacritty_shell(Shell::System, true, system_shell),
+            expected_system(login_args.clone())
+        );
+        if !cfg!(target_os = "macos") {
+            assert_eq!(alacritty_shell(Shell::System, true, || None), None);
+        }
+
+        let program = Shell::Program("fish".to_string());
+        assert_eq!(
+            alacritty_shell(program.clone(), false, system_shell),
+            Some(alacritty_terminal::tty::Shell::new(
+                "fish".to_string(),
+                Vec::new()
+            ))
+        );
+        assert_eq!(
+            alacritty_shell(program, true, system_shell),
+            Some(alacritty_terminal::tty::Shell::new(
+                "fish".to_string(),
+                login_args
+            ))
+        );
+
+        // Arguments are passed as given, without the login flag
+        let with_arguments = Shell::WithArguments {
+            program: "bash".to_string(),
+            args: vec!["-c".to_string(), "cargo test".to_string()],
+        };
+        assert_eq!(
+            alacritty_shell(with_arguments, true, system_shell),
+            Some(alacritty_terminal::tty::Shell::new(
+                "bash".to_string(),
+                vec!["-c".to_string(), "cargo test".to_string()]
+            ))
+        );
+    }
+
+    #[test]
+    fn test_window_size_at_fractional_cell_size() {
+        let size = TerminalSize::new(px(17.8), px(8.6), size(px(90.), px(180.)));
+        let window_size = WindowSize::from(size);
+        assert_eq!((window_size.num_cols, window_size.num_lines), (10, 10));
+        assert_eq!((window_size.cell_width, window_size.cell_height), (9, 18));
+
+        // What the PTY reports is within half a pixel per cell of the grid, here over it
+        let width = window_size.num_cols * window_size.cell_width;
+        let height = window_size.num_lines * window_size.cell_height;
+        assert_eq!((width, height), (90, 180));
+        let grid_width = size.num_columns() as f32 * f32::from(size.cell_width());
+        let grid_height = size.num_lines() as f32 * f32::from(size.line_height());
+        assert!((width as f32 - grid_width).abs() <= 0.5 * size.num_columns() as f32);
+        assert!((height as f32 - grid_height).abs() <= 0.5 * size.num_lines() as f32);
+
+        // Programs asking for the size in pixels get the grid's own
+        assert_eq!(text_area_size_report(&size), "\x1b[4;178;86t");
+    }
+
+    #[test]
+    fn test_text_area_size_reports() {
+        let size = TerminalSize::new(px(17.), px(8.4), size(px(85.), px(172.)));
+        let (events_tx, mut events_rx) = unbounded();
+        let listener = ZedListener(events_tx, Arc::new(AtomicBool::new(false)));
+        let mut term = Term::new(Config::default(), &size, listener);
+        let mut report = |query: &str| {
+            process_input(&mut term, query);
+            match events_rx.try_next() {
+                Ok(Some(AlacTermEvent::PtyWrite(text))) => text,
+                Ok(Some(AlacTermEvent::TextAreaSizeRequest(_))) => text_area_size_report(&size),
+                _ => panic!("no size report for {query:?}"),
+            }
+        };
+
+        // 10 columns of 8.4px by 10 lines of 17px, as cells and as pixels
+        assert_eq!(report("\x1b[18t"), "\x1b[8;10;10t");
+        assert_eq!(report("\x1b[14t"), "\x1b[4;170;84t");
+
+        // Wider than what fits in the `u16`s of a `WindowSize`
+        let wide = TerminalSize::new(px(17.), px(8.4), size(px(70000.), px(172.)));
+        assert_eq!(text_area_size_report(&wide), "\x1b[4;170;69997t");
+    }
+
+    #[test]
+    fn test_mode_reports() {
+        let (events_tx, mut events_rx) = unbounded();
+        let listener = ZedListener(events_tx, Arc::new(AtomicBool::new(false)));
+        let mut term = Term::new(Config::default(), &TerminalSize::default(), listener);
+        let mut reports = Vec::new();
+        let mut report = |term: &mut Term<ZedListener>, query: &str| {
+            process_input(term, query);
+            match events_rx.try_next() {
+                Ok(Some(AlacTermEvent::PtyWrite(text))) => reports.push(text),
+                _ => panic!("no mode report for {query:?}"),
+            }
+        };
+
+        // DECRPM answers 1 for set, 2 for reset and 0 for unrecognized modes
+        for mode in [
+            1, 6, 7, 12, 25, 1000, 1002, 1003, 1004, 1006, 1007, 1049, 2004,
+        ] {
+            report(&mut term, &format!("\x1b[?{mode}$p"));
+        }
+        report(&mut term, "\x1b[4$p");
+        report(&mut term, "\x1b[20$p");
+        report(&mut term, "\x1b[?9999$p");
+        process_input(&mut term, "\x1b[?2004h\x1b[?1004h\x1b[?7l");
+        for mode in [7, 1004, 2004] {
+            report(&mut term, &format!("\x1b[?{mode}$p"));
+        }
+
+        assert_eq!(
+            reports,
+            [
+                "\x1b[?1;2$y",
+                "\x1b[?6;2$y",
+                "\x1b[?7;1$y",
+                "\x1b[?12;2$y",
+                "\x1b[?25;1$y",
+                "\x1b[?1000;2$y",
+                "\x1b[?1002;2$y",
+                "\x1b[?1003;2$y",
+                "\x1b[?1004;2$y",
+                "\x1b[?1006;2$y",
+                "\x1b[?1007;1$y",
+                "\x1b[?1049;2$y",
+                "\x1b[?2004;2$y",
+                "\x1b[4;2$y",
+                "\x1b[20;2$y",
+                "\x1b[?9999;0$y",
+                "\x1b[?7;2$y",
+                "\x1b[?1004;1$y",
+                "\x1b[?2004;1$y",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_listener_drops_redundant_wakeups() {
+        let (events_tx, mut events_rx) = unbounded();
+        let wakeup_pending = Arc::new(AtomicBool::new(false));
+        let listener = ZedListener(events_tx, wakeup_pending.clone());
+
+        for _ in 0..1000 {
+            listener.send_event(AlacTermEvent::Wakeup);
+        }
+        listener.send_event(AlacTermEvent::Bell);
+        listener.send_event(AlacTermEvent::Wakeup);
+
+        assert!(matches!(
+            events_rx.try_next(),
+            Ok(Some(AlacTermEvent::Wakeup))
+        ));
+        assert!(matches!(
+            events_rx.try_next(),
+            Ok(Some(AlacTermEvent::Bell))
+        ));
+        assert!(events_rx.try_next().is_err());
+
+        // Once the pending wakeup is received, the next one is sent again
+        wakeup_pending.store(false, Ordering::Release);
+        listener.send_event(AlacTermEvent::Wakeup);
+        assert!(matches!(
+            events_rx.try_next(),
+            Ok(Some(AlacTermEvent::Wakeup))
+        ));
+    }
+
+    #[test]
+    fn test_focus_report() {
+        assert_eq!(focus_report(true, TermMode::empty()), None);
+        assert_eq!(focus_report(false, TermMode::SHOW_CURSOR), None);
+        assert_eq!(focus_report(true, TermMode::FOCUS_IN_OUT), Some("\x1b[I"));
+        assert_eq!(
+            focus_report(false, TermMode::FOCUS_IN_OUT | TermMode::SHOW_CURSOR),
+            Some("\x1b[O")
+        );
+    }
+
+    #[test]
+    fn test_quote_command() {
+        assert_eq!(quote_command("bash", &[]), "bash");
+        assert_eq!(
+            quote_command(
+                "/opt/my shell/bin/sh",
+                &["-c".to_string(), "echo hello world".to_string()]
+            ),
+            "'/opt/my shell/bin/sh' -c 'echo hello world'"
+        );
+    }
+
+    #[test]
+    fn test_drag_selection_with_scroll_margins() {
+        let mut term = term_with_input(10, 4, "one\r\ntwo\r\nthree\r\nfour\r\nfive\r\nsix");
+        let terminal_size = content_from_term(&term).size;
+
+        // Output scrolling inside margins that don't start at the top leaves the
+        // history alone, so the viewport scrolls through it the same way
+        process_input(&mut term, "\x1b[2;3r\x1b[3;1H\n");
+        assert_eq!(term.history_size(), 2);
+        assert_eq!(buffer_text(&term), "one\ntwo\nthree\nfive\n\nsix");
+
+        // Dragging above the grid scrolls the viewport and extends the selection into
+        // the history, as without margins
+        term.selection = Some(Selection::new(
+            SelectionType::Simple,
+            AlacPoint::new(Line(3), Column(2)),
+            Side::Right,
+        ));
+        let region = Bounds::new(point(px(0.), px(0.)), terminal_size.size);
+        let position = point(px(0.), px(0.));
+        let lines = drag_line_delta(position, region, terminal_size.line_height());
+        assert_eq!(lines, Some(2));
+        term.scroll_display(AlacScroll::Delta(lines.unwrap()));
+
+        let (head, side) =
+            grid_point_and_side(position, terminal_size, term.grid().display_offset());
+        assert_eq!(head, AlacPoint::new(Line(-2), Column(0)));
+        let mut selection = term.selection.take().unwrap();
+        selection.update(head, side);
+        term.selection = Some(selection);
+        assert_eq!(
+            term.selection_to_string().as_deref(),
+            Some("one\ntwo\nthree\nfive\n\nsix")
+        );
+    }
+
+    #[test]
+    fn test_held_viewport_past_full_history() {
+        let size = TerminalSize::new(px(10.), px(10.), size(px(100.), px(40.)));
+        let config = Config {
+            scrolling_history: 3,
+            ..Config::default()
+        };
+        let mut term = Term::new(config, &size, VoidListener);
+        let top_line = |term: &Term<VoidListener>| {
+            let line = Line(-(term.grid().display_offset() as i32));
+            term.grid()[line][Column(0)].c
+        };
+
+        // The history is full, so it doesn't grow as output scrolls the viewport
+        process_input(&mut term, "1\r\n2\r\n3\r\n4\r\n5\r\n6\r\n7\r\n8");
+        assert_eq!(term.grid().history_size(), 3);
+        hold_viewport(&mut term);
+        assert_eq!(top_line(&term), '4');
+
+        process_input(&mut term, "\r\n9\r\n10");
+        assert_eq!(term.grid().history_size(), 3);
+        assert_eq!(top_line(&term), '4');
+
+        // Until the held lines drop out of the history
+        process_input(&mut term, "\r\n11");
+        assert_eq!(top_line(&term), '5');
+    }
+
+    #[test]
+    fn test_dirty_lines() {
+        let mut term = term_with_input(10, 4, "one\r\ntwo\r\nthree");
+        let first = content_from_term(&term).cells;
+        let mut cells = Vec::new();
+        assert_eq!(update_cells(&mut cells, first.clone(), 10), [0, 1, 2, 3]);
+        assert_eq!(update_cells(&mut cells, first, 10), [] as [usize; 0]);
+
+        process_input(&mut term, "\x1b[2;1HTWO\x1b[4;1Hfour");
+        let second = content_from_term(&term).cells;
+        assert_eq!(update_cells(&mut cells, second, 10), [1, 3]);
+
+        // Scrolling the viewport moves every row
+        process_input(&mut term, "\r\nfive");
+        term.scroll_display(AlacScroll::Delta(1));
+        let scrolled = content_from_term(&term).cells;
+        assert_eq!(update_cells(&mut cells, scrolled, 10), [0, 1, 2, 3]);
+        assert_eq!(cells.len(), 40);
+    }
+
+    #[test]
+    fn test_visible_text() {
+        let term = term_with_input(10, 4, "ab  c\r\n\r\n你好x");
+        let mut content = content_from_term(&term);
+        assert_eq!(content.visible_text(), "ab  c\n\n你好x");
+
+        // Gaps between sparse cells are filled with spaces
+        let cell = |line, column, c| IndexedCell {
+            point: AlacPoint::new(Line(line), Column(column)),
+            cell: Cell {
+                c,
+                ..Default::default()
+            },
+        };
+        content.cells = vec![cell(0, 2, 'a'), cell(0, 5, 'b'), cell(2, 1, 'c')];
+        assert_eq!(content.visible_text(), "  a  b\n\n c");
+    }
+
+    #[test]
+    fn test_cell_at() {
+        let term = term_with_input(10, 2, "a\x1b[1;31mb\r\n\x1b[0m你");
+        let content = content_from_term(&term);
+        let point = |line, column| AlacPoint::new(Line(line), Column(column));
+
+        let cell = content.cell_at(point(0, 1)).unwrap();
+        assert_eq!(cell.point, point(0, 1));
+        assert_eq!(cell.c, 'b');
+        assert_eq!(cell.fg, AnsiColor::Named(NamedColor::Red));
+        assert!(cell.flags.contains(Flags::BOLD));
+
+        assert_eq!(content.cell_at(point(1, 0)).unwrap().c, '你');
+        assert!(content
+            .cell_at(point(1, 1))
+            .unwrap()
+            .flags
+            .contains(Flags::WIDE_CHAR_SPACER));
+        assert!(content.cell_at(point(2, 0)).is_none());
+    }
+
+    #[test]
+    fn test_cell_decorations() {
+        let term = term_with_input(
+            20,
+            1,
+            "\x1b[4ma\x1b[21mb\x1b[4:3mc\x1b[4:4md\x1b[4:5me\x1b[0m\x1b[9mf\x1b[0mg",
+        );
+        let content = content_from_term(&term);
+        let decorations = |column| {
+            let cell = &content.cells[column];
+            assert_eq!(cell.point.column, Column(column));
+            (cell.underline(), cell.is_strikethrough())
+        };
+
+        assert_eq!(decorations(0), (Some(CellUnderline::Single), false));
+        assert_eq!(decorations(1), (Some(CellUnderline::Double), false));
+        assert_eq!(decorations(2), (Some(CellUnderline::Curly), false));
+        assert_eq!(decorations(3), (Some(CellUnderline::Dotted), false));
+        assert_eq!(decorations(4), (Some(CellUnderline::Dashed), false));
+        assert_eq!(decorations(5), (None, true));
+        assert_eq!(decorations(6), (None, false));
+    }
+
+    #[test]
+    fn test_wide_char_point_and_side() {
+        let term = term_with_input(10, 2, "a你好b");
+        let content = content_from_term(&term);
+        let point = |column| AlacPoint::new(Line(0), Column(column));
+
+        // Both halves of a wide character land on it
+        assert_eq!(
+            wide_char_point_and_side(point(1), Side::Left, &content),
+            (point(1), Side::Left)
+        );
+        assert_eq!(
+            wide_char_point_and_side(point(2), Side::Left, &content),
+            (point(1), Side::Right)
+        );
+        assert_eq!(
+            wide_char_point_and_side(point(4), Side::Right, &content),
+            (point(3), Side::Right)
+        );
+        assert_eq!(
+            wide_char_point_and_side(point(5), Side::Left, &content),
+            (point(5), Side::Left)
+        );
+    }
+
+    #[test]
+    fn test_url_at() {
+        let mut url_regex = RegexSearch::new(URL_REGEX).unwrap();
+        // The URL soft wraps onto the second line
+        let term = term_with_input(16, 3, "see https://zed.dev/docs now");
+        let point = |line, column| AlacPoint::new(Line(line), Column(column));
+
+        for url_point in [point(0, 4), point(0, 10), point(1, 3)] {
+            assert_eq!(
+                url_at(&term, url_point, &mut url_regex).as_deref(),
+                Some("https://zed.dev/docs")
+            );
+        }
+        assert_eq!(url_at(&term, point(0, 1), &mut url_regex), None);
+        assert_eq!(url_at(&term, point(1, 10), &mut url_regex), None);
+
+        let term = term_with_input(
+            30,
+            2,
+            "\x1b]8;;https://zed.dev\x1b\\the site\x1b]8;;\x1b\\ here",
+        );
+        assert_eq!(
+            url_at(&term, point(0, 5), &mut url_regex).as_deref(),
+            Some("https://zed.dev")
+        );
+        assert_eq!(url_at(&term, point(0, 10), &mut url_regex), None);
+    }
+
+    #[test]
+    fn test_formatted_selection() {
+        let mut term = term_with_input(
+            20,
+            2,
+            "\x1b[1;31mred\x1b[0m plain\r\n\x1b[4;38;5;200mx<y\x1b[0m",
+        );
+        let theme = &theme::zed_pro_family().themes[1];
+        let mut selection = Selection::new(
+            SelectionType::Simple,
+            AlacPoint::new(Line(0), Column(0)),
+            Side::Left,
+        );
+        selection.update(AlacPoint::new(Line(1), Column(19)), Side::Right);
+        term.selection = Some(selection);
+
+        assert_eq!(
+            formatted_selection(&term, CopyFormat::Plain, theme).as_deref(),
+            Some("red plain\nx<y")
+        );
+        assert_eq!(
+            formatted_selection(&term, CopyFormat::Ansi, theme).as_deref(),
+            Some("\x1b[1;31mred\x1b[0m plain\n\x1b[4;38;5;200mx<y\x1b[0m")
+        );
+
+        let Rgb { r, g, b } = to_alac_rgb(theme.colors().terminal_ansi_red);
+        let Rgb {
+            r: r200,
+            g: g200,
+            b: b200,
+        } = to_alac_rgb(get_color_at_index(200, theme));
+        assert_eq!(
+            formatted_selection(&term, CopyFormat::Html, theme),
+            Some(format!(
+                "<pre><span style=\"color:#{r:02x}{g:02x}{b:02x};font-weight:bold\">red</span> plain\n\
+                <span style=\"color:#{r200:02x}{g200:02x}{b200:02x};text-decoration:underline\">x&lt;y</span></pre>"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_fill_title_template() {
+        let tokens = [
+            ("cwd", "/home/user/project"),
+            ("cwd_basename", "project"),
+            ("process", "cargo"),
+            ("title", "{cwd} building"),
+        ];
+        assert_eq!(
+            fill_title_template("{cwd_basename} — {process}", &tokens),
+            "project — cargo"
+        );
+        assert_eq!(
+            fill_title_template("{process}: {title} in {cwd}", &tokens),
+            "cargo: {cwd} building in /home/user/project"
+        );
+        assert_eq!(
+            fill_title_template("{unknown} {{cwd_basename}} {", &tokens),
+            "{unknown} {project} {"
+        );
+        assert_eq!(fill_title_template("{process}", &[("process", "")]), "");
+    }
+
+    #[test]
+    fn test_word_at() {
+        let term = term_with_input(20, 1, "foo bar.baz (qux)");
+        let point = |column| AlacPoint::new(Line(0), Column(column));
+
+        assert_eq!(
+            word_at(&term, point(0)),
+            Some(("foo".into(), point(0)..=point(2)))
+        );
+        assert_eq!(
+            word_at(&term, point(7)),
+            Some(("bar.baz".into(), point(4)..=point(10)))
+        );
+        assert_eq!(
+            word_at(&term, point(14)),
+            Some(("qux".into(), point(13)..=point(15)))
+        );
+
+        // Separators and empty cells aren't words
+        assert_eq!(word_at(&term, point(3)), None);
+        assert_eq!(word_at(&term, point(12)), None);
+        assert_eq!(word_at(&term, point(19)), None);
+    }
+
+    #[test]
+    fn test_path_at() {
+        let mut path_regex = RegexSearch::new(PATH_REGEX).unwrap();
+        let term = term_with_input(
+            40,
+            3,
+            "error at src/main.rs:42:10\r\nsee ./docs/guide.md or README",
+        );
+        let point = |line, column| AlacPoint::new(Line(line), Column(column));
+        let path_at_point = |column_point, path_regex: &mut RegexSearch| {
+            path_at(&term, column_point, path_regex).map(|path| {
+                (
+                    path.path.to_string_lossy().into_owned(),
+                    path.row,
+                    path.column,
+                )
+            })
+        };
+
+        assert_eq!(
+            path_at_point(point(0, 13), &mut path_regex),
+            Some(("src/main.rs".to_string(), Some(42), Some(10)))
+        );
+        assert_eq!(
+            path_at_point(point(1, 8), &mut path_regex),
+            Some(("./docs/guide.md".to_string(), None, None))
+        );
+        assert_eq!(path_at_point(point(0, 2), &mut path_regex), None);
+        assert_eq!(path_at_point(point(1, 25), &mut path_regex), None);
+    }
+
+    #[test]
+    fn test_buffer_text() {
+        let term = term_with_input(10, 3, "ab  \r\n你好\r\nabcdefghijkl\r\n\r\nlast");
+        // The first lines have scrolled into the history
+        assert_eq!(term.grid().history_size(), 3);
+
+        assert_eq!(buffer_text(&term), "ab\n你好\nabcdefghijkl\n\nlast");
+    }
+
+    #[test]
+    fn test_search_matches_across_wrapped_lines() {
+        // The 10 column terminal soft wraps the key across its first two lines
+        let term = term_with_input(10, 5, "{\"message_id\": 1}\r\n{\"message\r\n_id\": 2}");
+        let mut searcher = RegexSearch::new("message_id").unwrap();
+        let matches: Vec<_> = all_search_matches(&term, &mut searcher).collect();
+
+        assert_eq!(
+            matches,
+            vec![AlacPoint::new(Line(0), Column(2))..=AlacPoint::new(Line(1), Column(1))]
+        );
+    }
+
+    #[test]
+    fn test_search_options_match_content() {
+        let term = term_with_input(20, 5, "Foo food foo");
+        // The patterns the terminal view builds for "foo" with each combination of the
+        // whole word and case sensitive options
+        for (pattern, expected_columns) in [
+            ("(?i)foo", vec![0..=2, 4..=6, 9..=11]),
+            ("(?-i)foo", vec![4..=6, 9..=11]),
+            (r"(?i)(?-u:\b)(?:foo)(?-u:\b)", vec![0..=2, 9..=11]),
+            (r"(?-i)(?-u:\b)(?:foo)(?-u:\b)", vec![9..=11]),
+        ] {
+            let mut searcher = RegexSearch::new(pattern).unwrap();
+            let columns: Vec<_> = all_search_matches(&term, &mut searcher)
+                .map(|range| range.start().column.0..=range.end().column.0)
+                .collect();
+            assert_eq!(columns, expected_columns, "pattern: {pattern}");
+        }
+    }
+
+    #[test]
+    fn test_refine_search_matches() {
+        let term = term_with_input(20, 5, "foo Food fool football");
+        let mut searcher = RegexSearch::new("(?i)foo").unwrap();
+        let matches: Vec<_> = all_search_matches(&term, &mut searcher).collect();
+        assert_eq!(matches.len(), 4);
+
+        let pattern = "(?i)food";
+        let mut searcher = RegexSearch::new(pattern).unwrap();
+        let refined = refine_search_matches(&term, &mut searcher, matches, pattern.len() * 2);
+        let expected: Vec<_> = all_search_matches(&term, &mut searcher).collect();
+        assert_eq!(refined, expected);
+        assert_eq!(
+            refined,
+            vec![AlacPoint::new(Line(0), Column(4))..=AlacPoint::new(Line(0), Column(7))]
+        );
+
+        // The extended query can start inside a previous match when it overlaps itself
+        let term = term_with_input(20, 5, "aaab");
+        let mut searcher = RegexSearch::new("aa").unwrap();
+        let matches: Vec<_> = all_search_matches(&term, &mut searcher).collect();
+        assert_eq!(
+            matches,
+            vec![AlacPoint::new(Line(0), Column(0))..=AlacPoint::new(Line(0), Column(1))]
+        );
+
+        let pattern = "aab";
+        let mut searcher = RegexSearch::new(pattern).unwrap();
+        let refined = refine_search_matches(&term, &mut searcher, matches, pattern.len() * 2);
+        assert_eq!(
+            refined,
+            vec![AlacPoint::new(Line(0), Column(1))..=AlacPoint::new(Line(0), Column(3))]
+        );
+    }
+
+    #[test]
+    fn test_next_search_match() {
+        let mut term = term_with_input(20, 5, "aa foo bb foo cc");
+        let mut searcher = RegexSearch::new("foo").unwrap();
+        let first = AlacPoint::new(Line(0), Column(3))..=AlacPoint::new(Line(0), Column(5));
+        let second = AlacPoint::new(Line(0), Column(10))..=AlacPoint::new(Line(0), Column(12));
+
+        let mut find = |term: &mut Term<VoidListener>, direction| {
+            let search_match = next_search_match(term, &mut searcher, direction).unwrap();
+            term.selection = Some(make_selection(&search_match));
+            search_match
+        };
+
+        // Each search moves past the selected match, wrapping around the buffer
+        assert_eq!(find(&mut term, AlacDirection::Right), first);
+        assert_eq!(find(&mut term, AlacDirection::Right), second);
+        assert_eq!(find(&mut term, AlacDirection::Right), first);
+
+        term.selection = None;
+        assert_eq!(find(&mut term, AlacDirection::Left), second);
+        assert_eq!(find(&mut term, AlacDirection::Left), first);
+        assert_eq!(find(&mut term, AlacDirection::Left), second);
+    }
+
+    #[test]
+    fn test_aligned_display_offset() {
+        // A match 50 lines up in the scrollback of a 20 line terminal.
+        assert_eq!(
+            aligned_display_offset(-50, 20, 100, ScrollAlignment::Top),
+            50
+        );
+        assert_eq!(
+            aligned_display_offset(-50, 20, 100, ScrollAlignment::Center),
+            60
+        );
+        assert_eq!(
+            aligned_display_offset(-50, 20, 100, ScrollAlignment::Bottom),
+            69
+        );
+
+        // Offsets are clamped to the scrollback.
+        assert_eq!(
+            aligned_display_offset(-95, 20, 100, ScrollAlignment::Center),
+            100
+        );
+        assert_eq!(
+            aligned_display_offset(15, 20, 100, ScrollAlignment::Center),
+            0
+        );
+    }
+
+    #[test]
+    fn test_coalesce_events() {
+        let terminal_size =
+            |lines: f32| TerminalSize::new(px(10.), px(5.), size(px(100.), px(lines * 10.)));
+        let mut events = VecDeque::from([
+            InternalEvent::Resize(terminal_size(10.)),
+            InternalEvent::Resize(terminal_size(20.)),
+            InternalEvent::Scroll(AlacScroll::Delta(3)),
+            InternalEvent::Scroll(AlacScroll::Delta(-1)),
+            InternalEvent::Scroll(AlacScroll::Bottom),
+            InternalEvent::Scroll(AlacScroll::Delta(2)),
+            InternalEvent::UpdateSelection(point(px(1.), px(1.))),
+            InternalEvent::UpdateSelection(point(px(2.), px(2.))),
+            InternalEvent::Copy(CopyFormat::Plain),
+            InternalEvent::UpdateSelection(point(px(3.), px(3.))),
+            InternalEvent::Resize(terminal_size(30.)),
+        ]);
+        coalesce_events(&mut events);
+
+        let mut events = events.into_iter();
+        assert!(
+            matches!(events.next(), Some(InternalEvent::Resize(size)) if size == terminal_size(20.))
+        );
+        assert!(matches!(
+            events.next(),
+            Some(InternalEvent::Scroll(AlacScroll::Delta(2)))
+        ));
+        assert!(matches!(
+            events.next(),
+            Some(InternalEvent::Scroll(AlacScroll::Bottom))
+        ));
+        assert!(matches!(
+            events.next(),
+            Some(InternalEvent::Scroll(AlacScroll::Delta(2)))
+        ));
+        assert!(
+            matches!(events.next(), Some(InternalEvent::UpdateSelection(position)) if position == point(px(2.), px(2.)))
+        );
+        assert!(matches!(events.next(), Some(InternalEvent::Copy(_))));
+        assert!(matches!(
+            events.next(),
+            Some(InternalEvent::UpdateSelection(_))
+        ));
+        assert!(
+            matches!(events.next(), Some(InternalEvent::Resize(size)) if size == terminal_size(30.))
+        );
+        assert!(events.next().is_none());
+    }
+
+    #[test]
+    fn test_accumulate_scroll_is_monotonic() {
+        let line_height = px(10.);
+        let mut scroll_px = px(0.);
+
+        // A slow start followed by a fast flick.
+        let deltas = [3., 4., 5., 37., 150., 260., 91.];
+        let lines: Vec<i32> = deltas
+            .iter()
+            .map(|delta| accumulate_scroll(&mut scroll_px, px(*delta), line_height))
+            .collect();
+        assert!(lines.iter().all(|lines| *lines >= 0));
+        assert_eq!(lines.iter().sum::<i32>(), 55);
+        assert_eq!(scroll_px, px(0.));
+
+        let lines: i32 = [-25., -180., -7.]
+            .iter()
+            .map(|delta| accumulate_scroll(&mut scroll_px, px(*delta), line_height))
+            .sum();
+        assert_eq!(lines, -21);
+        assert_eq!(scroll_px, px(-2.));
+    }
+
+    #[test]
+    fn test_bracketed_paste_cannot_break_out() {
+        let pasted = paste_text("echo safe\x1b[201~echo unsafe\x03\u{9b}201~\n", true);
+        assert_eq!(pasted, "\x1b[200~echo safe[201~echo unsafe201~\n\x1b[201~");
+        assert_eq!(pasted.matches('\x1b').count(), 2);
+
+        assert_eq!(paste_text("a\tb\r\nc", true), "\x1b[200~a\tb\r\nc\x1b[201~");
+        assert_eq!(paste_text("a\r\nb\nc", false), "a\rb\rc");
+    }
+
+    #[test]
+    fn test_terminal_env() {
+        let user_env = HashMap::from_iter([
+            ("EDITOR".to_string(), "zed --wait".to_string()),
+            ("PAGER".to_string(), String::new()),
+            ("TERM_PROGRAM".to_string(), "other".to_string()),
+        ]);
+        let env = terminal_env(user_env, |_| None, "1.2.3".to_string());
+
+        assert_eq!(env.get("EDITOR").map(String::as_str), Some("zed --wait"));
+        assert_eq!(env.get("PAGER").map(String::as_str), Some(""));
+        assert_eq!(env.get("TERM_PROGRAM").map(String::as_str), Some("zed"));
+        assert_eq!(
+            env.get("TERM_PROGRAM_VERSION").map(String::as_str),
+            Some("1.2.3")
+        );
+        assert_eq!(env.get("LC_ALL").map(String::as_str), Some("en_US.UTF-8"));
+
+        // Empty values reach the PTY as variables set to empty
+        let options = pty_options(Shell::Program("sh".to_string()), false, None, env);
+        assert_eq!(options.env.get("PAGER").map(String::as_str), Some(""));
+        assert_eq!(
+            options.env.get("EDITOR").map(String::as_str),
+            Some("zed --wait")
+        );
+    }
+
+    #[test]
+    fn test_locale_detection() {
+        let no_host_locale = |_: &str| None;
+        let host_lang = |name: &str| (name == "LANG").then(|| "de_DE.UTF-8".to_string());
+
+        assert!(is_locale_unset(&HashMap::default(), no_host_locale));
+        assert!(!is_locale_unset(&HashMap::default(), host_lang));
+
+        let env = HashMap::from_iter([("LC_ALL".to_string(), "fr_FR.UTF-8".to_string())]);
+        assert!(!is_locale_unset(&env, no_host_locale));
+
+        // An empty value in the terminal env hides the host locale.
+        let env = HashMap::from_iter([("LANG".to_string(), String::new())]);
+        assert!(is_locale_unset(&env, host_lang));
+    }
+
+    #[test]
+    fn test_osc8_hyperlinks() {
+        let term = term_with_input(
+            20,
+            5,
+            "\x1b]8;;https://zed.dev\x1b\\zed\x1b]8;;\x1b\\ and \x1b]8;id=1;file:///tmp\x1b\\tmp\x1b]8;;\x1b\\",
+        );
+        let cells = term
+            .renderable_content()
+            .display_iter
+            .map(|ic| IndexedCell {
+                point: ic.point,
+                cell: ic.cell.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            hyperlinks_in_cells(&cells),
+            vec![
+                (
+                    AlacPoint::new(Line(0), Column(0))..=AlacPoint::new(Line(0), Column(2)),
+                    "https://zed.dev".to_string()
+                ),
+                (
+                    AlacPoint::new(Line(0), Column(8))..=AlacPoint::new(Line(0), Column(10)),
+                    "file:///tmp".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_visible_urls_across_wrapped_lines() {
+        let term = term_with_input(20, 5, "see https://zed.dev/some/long/path ok");
+        let mut url_regex = RegexSearch::new(URL_REGEX).unwrap();
+
+        let urls = visible_regex_matches_with_text(&term, &mut url_regex);
+
+        assert_eq!(urls.len(), 1);
+        let (url_match, url) = &urls[0];
+        assert_eq!(url, "https://zed.dev/some/long/path");
+        assert_eq!(*url_match.start(), AlacPoint::new(Line(0), Column(4)));
+        assert_eq!(*url_match.end(), AlacPoint::new(Line(1), Column(13)));
+    }
+
     #[test]
     fn test_rgb_for_index() {
         // Test every possible value in the color cube.
@@ -1881,6 +4492,54 @@ mod tests {
         );
     }
 
+    /// The content `make_content` would produce for the term's viewport, at 10px cells.
+    fn content_from_term(term: &Term<VoidListener>) -> TerminalContent {
+        TerminalContent {
+            cells: term
+                .renderable_content()
+                .display_iter
+                .map(|ic| IndexedCell {
+                    point: ic.point,
+                    cell: ic.cell.clone(),
+                })
+                .collect(),
+            size: TerminalSize::new(
+                px(10.),
+                px(10.),
+                size(
+                    px(term.columns() as f32 * 10.),
+                    px(term.screen_lines() as f32 * 10.),
+                ),
+            ),
+            ..Default::default()
+        }
+    }
+
+    /// Creates a headless terminal of the given size and feeds `input` through its parser,
+    /// as if it was printed by a program running in the terminal.
+    fn term_with_input(columns: usize, lines: usize, input: &str) -> Term<VoidListener> {
+        let size = TerminalSize::new(
+            Pixels::from(10.),
+            Pixels::from(10.),
+            size(
+                Pixels::from(columns as f32 * 10.),
+                Pixels::from(lines as f32 * 10.),
+            ),
+        );
+        let mut term = Term::new(Config::default(), &size, VoidListener);
+        process_input(&mut term, input);
+        term
+    }
+
+    /// Feeds `input` through a parser into `term`, as if it was printed by a program
+    /// running in the terminal.
+    fn process_input<T: EventListener>(term: &mut Term<T>, input: &str) {
+        let mut processor: Processor = Processor::new();
+        for byte in input.bytes() {
+            processor.advance(term, byte);
+        }
+    }
+
     fn get_cells(size: TerminalSize, rng: &mut ThreadRng) -> Vec<Vec<char>> {
         let mut cells = Vec::new();
 
@@ -31,17 +31,33 @@ pub struct TerminalSettings {
     pub font_features: Option<FontFeatures>,
     pub font_weight: Option<FontWeight>,
     pub line_height: TerminalLineHeight,
+    pub minimum_contrast: f32,
+    pub dim_factor: f32,
     pub env: HashMap<String, String>,
     pub blinking: TerminalBlink,
+    pub blink_interval_ms: u64,
     pub alternate_scroll: AlternateScroll,
     pub option_as_meta: bool,
     pub copy_on_select: bool,
+    pub scroll_on_input: bool,
+    pub login_shell: bool,
+    pub paste_warn_on_newline: bool,
+    pub allow_clipboard_write: bool,
+    pub allow_clipboard_read: bool,
     pub button: bool,
     pub dock: TerminalDockPosition,
     pub default_width: Pixels,
     pub default_height: Pixels,
     pub detect_venv: VenvSettings,
     pub max_scroll_history_lines: Option<usize>,
+    pub scroll_multiplier: Option<f32>,
+    pub max_search_lines: Option<usize>,
+    pub cursor_shape: Option<CursorShape>,
+    pub bell: Option<TerminalBell>,
+    pub title_source: Option<TitleSource>,
+    pub title_template: Option<String>,
+    pub word_separators: Option<String>,
+    pub keep_open_on_exit: Option<KeepOpenOnExit>,
     pub toolbar: Toolbar,
 }
 
@@ -120,11 +136,23 @@ pub struct TerminalSettingsContent {
     ///
     /// Default: comfortable
     pub line_height: Option<TerminalLineHeight>,
+    /// The minimum contrast ratio (WCAG, from 1 to 21) between text and its
+    /// background. Text colors are lightened or darkened as needed to reach
+    /// it, keeping programs' unreadable color combinations legible.
+    ///
+    /// Default: 1 (colors are used as they are)
+    pub minimum_contrast: Option<f32>,
+    /// How opaque faint (SGR 2) text is drawn, from 0 to 1. Ghostty uses
+    /// ~0.69, Alacritty 0.66 and Kitty 0.75.
+    ///
+    /// Default: 0.7
+    pub dim_factor: Option<f32>,
     pub font_features: Option<FontFeatures>,
     /// Sets the terminal's font weight in CSS weight units 0-900.
     pub font_weight: Option<f32>,
     /// Any key-value pairs added to this list will be added to the terminal's
-    /// environment. Use `:` to separate multiple values.
+    /// environment. Use `:` to separate multiple values, or an empty value to
+    /// set a variable inherited from Zed's environment to empty.
     ///
     /// Default: {}
     pub env: Option<HashMap<String, String>>,
@@ -132,6 +160,11 @@ pub struct TerminalSettingsContent {
     ///
     /// Default: terminal_controlled
     pub blinking: Option<TerminalBlink>,
+    /// How long the cursor stays shown, then hidden, while blinking, in
+    /// milliseconds. Values below 100 are raised to 100.
+    ///
+    /// Default: 500
+    pub blink_interval_ms: Option<u64>,
     /// Sets whether Alternate Scroll mode (code: ?1007) is active by default.
     /// Alternate Scroll mode converts mouse scroll events into up / down key
     /// presses when in the alternate screen (e.g. when running applications
@@ -139,15 +172,43 @@ pub struct TerminalSettingsContent {
     ///
     /// Default: off
     pub alternate_scroll: Option<AlternateScroll>,
-    /// Sets whether the option key behaves as the meta key.
+    /// Sets whether the option key behaves as the meta key, sending
+    /// Alt-modified keys prefixed with an escape instead of the character
+    /// the platform would type.
     ///
-    /// Default: true
+    /// Default: false
     pub option_as_meta: Option<bool>,
     /// Whether or not selecting text in the terminal will automatically
     /// copy to the system clipboard.
     ///
     /// Default: false
     pub copy_on_select: Option<bool>,
+    /// Whether typing in the terminal scrolls it back to the bottom.
+    ///
+    /// Default: true
+    pub scroll_on_input: Option<bool>,
+    /// Whether to start the shell as a login shell (passing `-l`), so that login
+    /// files like `.profile` or `.zprofile` are read. This doesn't apply to shells
+    /// given arguments, nor on Windows. On macOS the system shell is always
+    /// started as a login shell.
+    ///
+    /// Default: false
+    pub login_shell: Option<bool>,
+    /// Whether to ask for confirmation before pasting text containing newlines,
+    /// which would run it as one or more commands.
+    ///
+    /// Default: false
+    pub paste_warn_on_newline: Option<bool>,
+    /// Whether programs running in the terminal can write to the system
+    /// clipboard with OSC 52 escape sequences.
+    ///
+    /// Default: true
+    pub allow_clipboard_write: Option<bool>,
+    /// Whether programs running in the terminal can read the system clipboard
+    /// with OSC 52 escape sequences. When disabled, they read an empty clipboard.
+    ///
+    /// Default: false
+    pub allow_clipboard_read: Option<bool>,
     /// Whether to show the terminal button in the status bar.
     ///
     /// Default: true
@@ -175,6 +236,46 @@ pub struct TerminalSettingsContent {
     ///
     /// Default: 10_000
     pub max_scroll_history_lines: Option<usize>,
+    /// How many lines to scroll per line of scroll wheel or trackpad movement.
+    /// Applications that capture the mouse always receive unscaled scroll events.
+    /// Values below 0.01 are treated as 0.01.
+    ///
+    /// Default: 4 on macOS, 1 on other platforms
+    pub scroll_multiplier: Option<f32>,
+    /// The maximum number of lines above and below the viewport to search
+    /// for matches. 0 searches the entire scrollback.
+    ///
+    /// Default: 100
+    pub max_search_lines: Option<usize>,
+    /// The shape of the cursor, used unless the running program requests another one.
+    ///
+    /// Default: block
+    pub cursor_shape: Option<CursorShape>,
+    /// How the terminal reacts to the bell character.
+    ///
+    /// Default: audible
+    pub bell: Option<TerminalBell>,
+    /// What the terminal tab title is based on, when not running a task. Falls back
+    /// to the foreground process when the preferred source is unavailable.
+    ///
+    /// Default: process
+    pub title_source: Option<TitleSource>,
+    /// A template for the terminal tab title, used instead of `title_source` when
+    /// set. `{cwd}`, `{cwd_basename}`, `{process}` and `{title}` are replaced by
+    /// the working directory, its name, the foreground process and the title set
+    /// by the running program.
+    ///
+    /// Default: none
+    pub title_template: Option<String>,
+    /// The characters that end a word when double-clicking to select it.
+    ///
+    /// Default: ",│`|:\"' ()[]{}<>\t"
+    pub word_separators: Option<String>,
+    /// Whether to keep the terminal open after its shell exits, to read the final output.
+    /// Tasks use their own `hide` setting instead.
+    ///
+    /// Default: never
+    pub keep_open_on_exit: Option<KeepOpenOnExit>,
     /// Toolbar related settings
     pub toolbar: Option<ToolbarContent>,
 }
@@ -238,6 +339,42 @@ impl TerminalLineHeight {
     }
 }
 
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum KeepOpenOnExit {
+    /// Always close the terminal.
+    #[default]
+    Never,
+    /// Keep the terminal open when the shell exits with a non-zero status.
+    OnFailure,
+    /// Always keep the terminal open.
+    Always,
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TitleSource {
+    /// The working directory and command line of the foreground process.
+    #[default]
+    Process,
+    /// The title set by the running program.
+    Application,
+    /// The name of the working directory.
+    WorkingDirectory,
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TerminalBell {
+    /// Ignore the bell.
+    Off,
+    /// Briefly flash the terminal background.
+    Visual,
+    /// Notify the terminal's consumers, which may mark the tab or play a sound.
+    #[default]
+    Audible,
+}
+
 #[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum TerminalBlink {
@@ -250,6 +387,20 @@ pub enum TerminalBlink {
     On,
 }
 
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CursorShape {
+    /// Cursor is a block like `█`.
+    #[default]
+    Block,
+    /// Cursor is an underscore like `_`.
+    Underline,
+    /// Cursor is a vertical bar like `⎸`.
+    Bar,
+    /// Cursor is a hollow box like `▯`.
+    Hollow,
+}
+
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum AlternateScroll {
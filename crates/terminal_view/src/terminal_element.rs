@@ -21,7 +21,7 @@ use terminal::{
         },
     },
     terminal_settings::TerminalSettings,
-    HoveredWord, IndexedCell, Terminal, TerminalContent, TerminalSize,
+    CellUnderline, HoveredWord, IndexedCell, Terminal, TerminalContent, TerminalSize,
 };
 use theme::{ActiveTheme, Theme, ThemeSettings};
 use ui::{ParentElement, Tooltip};
@@ -205,6 +205,8 @@ impl TerminalElement {
         cx: &WindowContext,
     ) -> (Vec<LayoutCell>, Vec<LayoutRect>) {
         let theme = cx.theme();
+        let settings = TerminalSettings::get_global(cx);
+        let (minimum_contrast, dim_factor) = (settings.minimum_contrast, settings.dim_factor);
         let mut cells = vec![];
         let mut rects = vec![];
 
@@ -280,6 +282,14 @@ impl TerminalElement {
                 {
                     if !is_blank(&cell) {
                         let cell_text = cell.c.to_string();
+                        let fg = cell_foreground(
+                            cell.flags,
+                            fg,
+                            bg,
+                            minimum_contrast,
+                            dim_factor,
+                            theme,
+                        );
                         let cell_style =
                             TerminalElement::cell_style(&cell, fg, theme, text_style, hyperlink);
 
@@ -337,36 +347,37 @@ impl TerminalElement {
     /// Converts the Alacritty cell styles to GPUI text styles and background color.
     fn cell_style(
         indexed: &IndexedCell,
-        fg: terminal::alacritty_terminal::vte::ansi::Color,
-        // bg: terminal::alacritty_terminal::ansi::Color,
+        fg: Hsla,
         colors: &Theme,
         text_style: &TextStyle,
         hyperlink: Option<(HighlightStyle, &RangeInclusive<AlacPoint>)>,
     ) -> TextRun {
         let flags = indexed.cell.flags;
-        let mut fg = convert_color(&fg, &colors);
-
-        // Ghostty uses (175/255) as the multiplier (~0.69), Alacritty uses 0.66, Kitty
-        // uses 0.75. We're using 0.7 because it's pretty well in the middle of that.
-        if flags.intersects(Flags::DIM) {
-            fg.a *= 0.7;
-        }
+        // There's no double, dotted or dashed underline in GPUI, so double underlines
+        // are drawn thicker and the rest as single ones.
+        let underline = indexed
+            .underline()
+            .or_else(|| indexed.cell.hyperlink().map(|_| CellUnderline::Single))
+            .map(|kind| UnderlineStyle {
+                color: Some(
+                    indexed
+                        .cell
+                        .underline_color()
+                        .map_or(fg, |color| convert_color(&color, colors)),
+                ),
+                thickness: Pixels::from(if kind == CellUnderline::Double {
+                    2.0
+                } else {
+                    1.0
+                }),
+                wavy: kind == CellUnderline::Curly,
+            });
 
-        let underline = (flags.intersects(Flags::ALL_UNDERLINES)
-            || indexed.cell.hyperlink().is_some())
-        .then(|| UnderlineStyle {
+        let strikethrough = indexed.is_strikethrough().then(|| StrikethroughStyle {
             color: Some(fg),
             thickness: Pixels::from(1.0),
-            wavy: flags.contains(Flags::UNDERCURL),
         });
 
-        let strikethrough = flags
-            .intersects(Flags::STRIKEOUT)
-            .then(|| StrikethroughStyle {
-                color: Some(fg),
-                thickness: Pixels::from(1.0),
-            });
-
         let weight = if flags.intersects(Flags::BOLD) {
             FontWeight::BOLD
         } else {
@@ -474,6 +485,11 @@ impl TerminalElement {
                         terminal.mouse_move(&e, origin);
                         cx.notify();
                     })
+                } else if terminal.read(cx).last_content.hovered_match.is_some() {
+                    terminal.update(cx, |terminal, cx| {
+                        terminal.set_hovered_point(None);
+                        cx.notify();
+                    })
                 }
             }
         });
@@ -657,6 +673,16 @@ impl Element for TerminalElement {
                     fade_out: None,
                 };
 
+                // Underlines links under the mouse in their own color
+                let hover_style = HighlightStyle {
+                    underline: Some(UnderlineStyle {
+                        thickness: px(1.0),
+                        color: None,
+                        wavy: false,
+                    }),
+                    ..HighlightStyle::default()
+                };
+
                 let text_style = TextStyle {
                     font_family,
                     font_features,
@@ -704,19 +730,22 @@ impl Element for TerminalElement {
 
                 let search_matches = self.terminal.read(cx).matches.clone();
 
-                let background_color = theme.colors().terminal_background;
-
-                let last_hovered_word = self.terminal.update(cx, |terminal, cx| {
-                    terminal.set_size(dimensions);
-                    terminal.sync(cx);
-                    if self.can_navigate_to_selected_word
-                        && terminal.can_navigate_to_selected_word()
-                    {
-                        terminal.last_content.last_hovered_word.clone()
-                    } else {
-                        None
-                    }
-                });
+                let (last_hovered_word, hovered_match) =
+                    self.terminal.update(cx, |terminal, cx| {
+                        terminal.set_size(dimensions);
+                        terminal.sync(cx);
+                        let last_hovered_word = if self.can_navigate_to_selected_word
+                            && terminal.can_navigate_to_selected_word()
+                        {
+                            terminal.last_content.last_hovered_word.clone()
+                        } else {
+                            None
+                        };
+                        (
+                            last_hovered_word,
+                            terminal.last_content.hovered_match.clone(),
+                        )
+                    });
 
                 let scroll_top = self.terminal_view.read(cx).scroll_top;
                 let hyperlink_tooltip = last_hovered_word.clone().map(|hovered_word| {
@@ -737,11 +766,20 @@ impl Element for TerminalElement {
                     cursor_char,
                     selection,
                     cursor,
+                    bell_flash,
                     ..
                 } = &self.terminal.read(cx).last_content;
                 let mode = *mode;
                 let display_offset = *display_offset;
 
+                let background_color = if *bell_flash {
+                    let mut flash = theme.colors().terminal_foreground;
+                    flash.fade_out(0.8);
+                    theme.colors().terminal_background.blend(flash)
+                } else {
+                    theme.colors().terminal_background
+                };
+
                 // searches, highlights to a single range representations
                 let mut relative_highlighted_ranges = Vec::new();
                 for search_match in search_matches {
@@ -760,7 +798,8 @@ impl Element for TerminalElement {
                     &cx.text_system(),
                     last_hovered_word
                         .as_ref()
-                        .map(|last_hovered_word| (link_style, &last_hovered_word.word_match)),
+                        .map(|last_hovered_word| (link_style, &last_hovered_word.word_match))
+                        .or_else(|| hovered_match.as_ref().map(|range| (hover_style, range))),
                     cx,
                 );
 
@@ -877,19 +916,23 @@ impl Element for TerminalElement {
             cx.paint_quad(fill(bounds, layout.background_color));
             let origin =
                 bounds.origin + Point::new(layout.gutter, px(0.)) - Point::new(px(0.), scroll_top);
+            let scroll_pixel_offset = px(self.terminal.read(cx).scroll_pixel_offset());
+            let content_origin = origin + Point::new(px(0.), scroll_pixel_offset);
 
             let terminal_input_handler = TerminalInputHandler {
                 terminal: self.terminal.clone(),
                 cursor_bounds: layout
                     .cursor
                     .as_ref()
-                    .map(|cursor| cursor.bounding_rect(origin)),
+                    .map(|cursor| cursor.bounding_rect(content_origin)),
                 workspace: self.workspace.clone(),
             };
 
             self.register_mouse_listeners(origin, layout.mode, &layout.hitbox, cx);
             if self.can_navigate_to_selected_word && layout.last_hovered_word.is_some() {
                 cx.set_cursor_style(gpui::CursorStyle::PointingHand, &layout.hitbox);
+            } else if self.terminal.read(cx).wants_mouse() {
+                cx.set_cursor_style(gpui::CursorStyle::Arrow, &layout.hitbox);
             } else {
                 cx.set_cursor_style(gpui::CursorStyle::IBeam, &layout.hitbox);
             }
@@ -918,14 +961,18 @@ impl Element for TerminalElement {
                     });
 
                     for rect in &layout.rects {
-                        rect.paint(origin, &layout, cx);
+                        rect.paint(content_origin, &layout, cx);
                     }
 
                     for (relative_highlighted_range, color) in
                         layout.relative_highlighted_ranges.iter()
                     {
                         if let Some((start_y, highlighted_range_lines)) =
-                            to_highlighted_range_lines(relative_highlighted_range, &layout, origin)
+                            to_highlighted_range_lines(
+                                relative_highlighted_range,
+                                &layout,
+                                content_origin,
+                            )
                         {
                             let hr = HighlightedRange {
                                 start_y,
@@ -939,12 +986,12 @@ impl Element for TerminalElement {
                     }
 
                     for cell in &layout.cells {
-                        cell.paint(origin, &layout, bounds, cx);
+                        cell.paint(content_origin, &layout, bounds, cx);
                     }
 
                     if self.cursor_visible {
                         if let Some(mut cursor) = cursor {
-                            cursor.paint(origin, cx);
+                            cursor.paint(content_origin, cx);
                         }
                     }
 
@@ -976,13 +1023,7 @@ struct TerminalInputHandler {
 
 impl InputHandler for TerminalInputHandler {
     fn selected_text_range(&mut self, cx: &mut WindowContext) -> Option<std::ops::Range<usize>> {
-        if self
-            .terminal
-            .read(cx)
-            .last_content
-            .mode
-            .contains(TermMode::ALT_SCREEN)
-        {
+        if self.terminal.read(cx).is_alternate_screen() {
             None
         } else {
             Some(0..0)
@@ -1128,6 +1169,77 @@ fn to_highlighted_range_lines(
     Some((start_y, highlighted_range_lines))
 }
 
+/// The relative luminance of the color, as defined by WCAG.
+fn relative_luminance(color: Hsla) -> f32 {
+    let rgba = color.to_rgb();
+    let linearize = |channel: f32| {
+        if channel <= 0.03928 {
+            channel / 12.92
+        } else {
+            ((channel + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * linearize(rgba.r) + 0.7152 * linearize(rgba.g) + 0.0722 * linearize(rgba.b)
+}
+
+/// The WCAG contrast ratio between two colors, from 1 (none) to 21 (black on white).
+fn contrast_ratio(a: Hsla, b: Hsla) -> f32 {
+    let (a, b) = (relative_luminance(a), relative_luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// Resolves a cell's text color against the theme, applying the minimum contrast
+/// against its background and fading faint (SGR 2) text by `dim_factor`.
+pub(crate) fn cell_foreground(
+    flags: Flags,
+    fg: terminal::alacritty_terminal::vte::ansi::Color,
+    bg: terminal::alacritty_terminal::vte::ansi::Color,
+    minimum_contrast: f32,
+    dim_factor: f32,
+    colors: &Theme,
+) -> Hsla {
+    let mut fg = convert_color(&fg, colors);
+    if minimum_contrast > 1. {
+        fg = ensure_minimum_contrast(fg, convert_color(&bg, colors), minimum_contrast);
+    }
+
+    if flags.intersects(Flags::DIM) {
+        fg.a *= dim_factor.clamp(0., 1.);
+    }
+    fg
+}
+
+/// Moves the foreground's lightness away from the background just enough to reach the
+/// contrast ratio, towards whichever of black or white contrasts more with the background.
+pub(crate) fn ensure_minimum_contrast(fg: Hsla, bg: Hsla, minimum_contrast: f32) -> Hsla {
+    if contrast_ratio(fg, bg) >= minimum_contrast {
+        return fg;
+    }
+
+    let with_lightness = |l| Hsla { l, ..fg };
+    let target = if contrast_ratio(with_lightness(1.), bg) > contrast_ratio(with_lightness(0.), bg)
+    {
+        1.
+    } else {
+        0.
+    };
+    if contrast_ratio(with_lightness(target), bg) < minimum_contrast {
+        return with_lightness(target);
+    }
+
+    // The foreground falls short and the target reaches it, search for the boundary between
+    let (mut low, mut high) = (fg.l, target);
+    for _ in 0..12 {
+        let mid = (low + high) / 2.;
+        if contrast_ratio(with_lightness(mid), bg) >= minimum_contrast {
+            high = mid;
+        } else {
+            low = mid;
+        }
+    }
+    with_lightness(high)
+}
+
 /// Converts a 2, 8, or 24 bit color ANSI color to the GPUI equivalent.
 pub fn convert_color(fg: &terminal::alacritty_terminal::vte::ansi::Color, theme: &Theme) -> Hsla {
     let colors = theme.colors();
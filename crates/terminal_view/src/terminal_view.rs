@@ -8,8 +8,8 @@ use futures::{stream::FuturesUnordered, StreamExt};
 use gpui::{
     anchored, deferred, div, impl_actions, AnyElement, AppContext, DismissEvent, EventEmitter,
     FocusHandle, FocusableView, KeyContext, KeyDownEvent, Keystroke, Model, MouseButton,
-    MouseDownEvent, Pixels, Render, ScrollWheelEvent, Styled, Subscription, Task, View,
-    VisualContext, WeakView,
+    MouseDownEvent, Pixels, PromptLevel, Render, ScrollWheelEvent, Styled, Subscription, Task,
+    View, VisualContext, WeakView,
 };
 use language::Bias;
 use persistence::TERMINAL_DB;
@@ -20,9 +20,9 @@ use terminal::{
         term::{search::RegexSearch, TermMode},
     },
     terminal_settings::{TerminalBlink, TerminalSettings, WorkingDirectory},
-    Clear, Copy, Event, MaybeNavigationTarget, Paste, ScrollLineDown, ScrollLineUp, ScrollPageDown,
-    ScrollPageUp, ScrollToBottom, ScrollToTop, ShowCharacterPalette, TaskStatus, Terminal,
-    TerminalSize,
+    Clear, Copy, CopyAsAnsi, CopyAsHtml, CopyFormat, Event, MaybeNavigationTarget, Paste,
+    ScrollLineDown, ScrollLineUp, ScrollPageDown, ScrollPageUp, ScrollToBottom, ScrollToTop,
+    ShowCharacterPalette, TaskStatus, Terminal, TerminalSize,
 };
 use terminal_element::{is_blank, TerminalElement};
 use ui::{h_flex, prelude::*, ContextMenu, Icon, IconName, Label, Tooltip};
@@ -47,15 +47,12 @@ use std::{
     path::{Path, PathBuf},
     rc::Rc,
     sync::Arc,
-    time::Duration,
 };
 
 const REGEX_SPECIAL_CHARS: &[char] = &[
     '\\', '.', '*', '+', '?', '|', '(', ')', '[', ']', '{', '}', '^', '$',
 ];
 
-const CURSOR_BLINK_INTERVAL: Duration = Duration::from_millis(500);
-
 ///Event to transmit the scroll from the element to the view
 #[derive(Clone, Debug, PartialEq)]
 pub struct ScrollTerminal(pub i32);
@@ -239,17 +236,14 @@ impl TerminalView {
     fn settings_changed(&mut self, cx: &mut ViewContext<Self>) {
         let settings = TerminalSettings::get_global(cx);
         self.show_title = settings.toolbar.title;
+        let blinking = settings.blinking;
+        self.terminal
+            .update(cx, |terminal, _| terminal.set_blink(blinking));
         cx.notify();
     }
 
     fn show_character_palette(&mut self, _: &ShowCharacterPalette, cx: &mut ViewContext<Self>) {
-        if self
-            .terminal
-            .read(cx)
-            .last_content
-            .mode
-            .contains(TermMode::ALT_SCREEN)
-        {
+        if self.terminal.read(cx).is_alternate_screen() {
             self.terminal.update(cx, |term, cx| {
                 term.try_keystroke(
                     &Keystroke::parse("ctrl-cmd-space").unwrap(),
@@ -405,12 +399,7 @@ impl TerminalView {
         if !focused
             || !self.blinking_on
             || self.blinking_paused
-            || self
-                .terminal
-                .read(cx)
-                .last_content
-                .mode
-                .contains(TermMode::ALT_SCREEN)
+            || self.terminal.read(cx).is_alternate_screen()
         {
             return true;
         }
@@ -429,8 +418,9 @@ impl TerminalView {
             cx.notify();
 
             let epoch = self.next_blink_epoch();
+            let interval = self.terminal.read(cx).blink_interval(cx);
             cx.spawn(|this, mut cx| async move {
-                Timer::after(CURSOR_BLINK_INTERVAL).await;
+                Timer::after(interval).await;
                 this.update(&mut cx, |this, cx| this.blink_cursors(epoch, cx))
                     .ok();
             })
@@ -443,8 +433,9 @@ impl TerminalView {
         cx.notify();
 
         let epoch = self.next_blink_epoch();
+        let interval = self.terminal.read(cx).blink_interval(cx);
         cx.spawn(|this, mut cx| async move {
-            Timer::after(CURSOR_BLINK_INTERVAL).await;
+            Timer::after(interval).await;
             this.update(&mut cx, |this, cx| this.resume_cursor_blinking(epoch, cx))
                 .ok();
         })
@@ -485,11 +476,51 @@ impl TerminalView {
         cx.notify();
     }
 
+    fn copy_as_ansi(&mut self, _: &CopyAsAnsi, cx: &mut ViewContext<Self>) {
+        self.terminal
+            .update(cx, |term, _| term.copy_as(CopyFormat::Ansi));
+        cx.notify();
+    }
+
+    fn copy_as_html(&mut self, _: &CopyAsHtml, cx: &mut ViewContext<Self>) {
+        self.terminal
+            .update(cx, |term, _| term.copy_as(CopyFormat::Html));
+        cx.notify();
+    }
+
     ///Attempt to paste the clipboard into the terminal
     fn paste(&mut self, _: &Paste, cx: &mut ViewContext<Self>) {
-        if let Some(item) = cx.read_from_clipboard() {
+        // Don't ask to confirm a paste that would be ignored
+        if self.terminal.read(cx).read_only() {
+            return;
+        }
+        let Some(item) = cx.read_from_clipboard() else {
+            return;
+        };
+        let text = item.text().to_string();
+
+        if TerminalSettings::get_global(cx).paste_warn_on_newline
+            && self.terminal.read(cx).paste_would_execute(&text)
+        {
+            let answer = cx.prompt(
+                PromptLevel::Warning,
+                "Paste multiple lines?",
+                Some("The pasted text contains newlines and may run commands."),
+                &["Paste", "Cancel"],
+            );
+            cx.spawn(|this, mut cx| async move {
+                if let Ok(0) = answer.await {
+                    this.update(&mut cx, |this, cx| {
+                        this.terminal
+                            .update(cx, |terminal, _cx| terminal.paste(&text));
+                    })
+                    .ok();
+                }
+            })
+            .detach();
+        } else {
             self.terminal
-                .update(cx, |terminal, _cx| terminal.paste(item.text()));
+                .update(cx, |terminal, _cx| terminal.paste(&text));
         }
     }
 
@@ -617,6 +648,11 @@ fn subscribe_for_terminal_events(
                 cx.emit(ItemEvent::UpdateTab);
             }
 
+            // Updating the tab also persists the new working directory.
+            Event::CwdChanged(_) => {
+                cx.emit(ItemEvent::UpdateTab);
+            }
+
             Event::NewNavigationTarget(maybe_navigation_target) => {
                 this.can_navigate_to_selected_word = match maybe_navigation_target {
                     Some(MaybeNavigationTarget::Url(_)) => true,
@@ -735,6 +771,13 @@ fn subscribe_for_terminal_events(
             },
             Event::BreadcrumbsChanged => cx.emit(ItemEvent::UpdateBreadcrumbs),
             Event::CloseTerminal => cx.emit(ItemEvent::CloseItem),
+            // A pane kept open by `keep_open_on_exit` shows that its process is gone.
+            Event::ProcessExited(_) => {
+                cx.emit(ItemEvent::UpdateTab);
+                cx.notify();
+            }
+            Event::Resized(_) => {}
+            Event::MatchesChanged => cx.notify(),
             Event::SelectionsChanged => cx.emit(SearchEvent::ActiveMatchChanged),
         });
     vec![terminal_subscription, terminal_events_subscription]
@@ -834,14 +877,29 @@ fn regex_to_literal(regex: &str) -> String {
 }
 
 pub fn regex_search_for_query(query: &project::search::SearchQuery) -> Option<RegexSearch> {
-    let query = query.as_str();
-    if query == "." {
+    let pattern = query.as_str();
+    if pattern == "." {
         return None;
     }
-    let searcher = RegexSearch::new(&query);
+    let pattern = search_pattern(pattern, query.whole_word(), query.case_sensitive());
+    let searcher = RegexSearch::new(&pattern);
     searcher.ok()
 }
 
+/// Applies the search options to a regex pattern.
+///
+/// Alacritty's regex search is smart-case by default (case sensitive only when the
+/// pattern contains an uppercase character), so the case flag is always set explicitly.
+/// Word boundaries are ASCII-only, as Unicode ones are not supported by its DFAs.
+fn search_pattern(pattern: &str, whole_word: bool, case_sensitive: bool) -> String {
+    let case_flag = if case_sensitive { "(?-i)" } else { "(?i)" };
+    if whole_word {
+        format!(r"{case_flag}(?-u:\b)(?:{pattern})(?-u:\b)")
+    } else {
+        format!("{case_flag}{pattern}")
+    }
+}
+
 impl TerminalView {
     fn key_down(&mut self, event: &KeyDownEvent, cx: &mut ViewContext<Self>) {
         self.clear_bell(cx);
@@ -859,7 +917,9 @@ impl TerminalView {
     }
 
     fn focus_in(&mut self, cx: &mut ViewContext<Self>) {
-        self.terminal.read(cx).focus_in();
+        self.terminal.update(cx, |terminal, _| {
+            terminal.focus_in();
+        });
         self.blink_cursors(self.blink_epoch, cx);
         cx.notify();
     }
@@ -878,6 +938,8 @@ impl Render for TerminalView {
         let terminal_view_handle = cx.view().clone();
 
         let focused = self.focus_handle.is_focused(cx);
+        let terminal = self.terminal.read(cx);
+        let exit_status = terminal.exit_status().filter(|_| terminal.task().is_none());
 
         div()
             .size_full()
@@ -887,6 +949,8 @@ impl Render for TerminalView {
             .on_action(cx.listener(TerminalView::send_text))
             .on_action(cx.listener(TerminalView::send_keystroke))
             .on_action(cx.listener(TerminalView::copy))
+            .on_action(cx.listener(TerminalView::copy_as_ansi))
+            .on_action(cx.listener(TerminalView::copy_as_html))
             .on_action(cx.listener(TerminalView::paste))
             .on_action(cx.listener(TerminalView::clear))
             .on_action(cx.listener(TerminalView::scroll_line_up))
@@ -929,6 +993,15 @@ impl Render for TerminalView {
                 )
                 .with_priority(1)
             }))
+            .when_some(exit_status, |this, exit_status| {
+                this.child(
+                    div().absolute().bottom_0().right_0().p_2().child(
+                        Label::new(format!("Process exited with status {exit_status}"))
+                            .size(LabelSize::Small)
+                            .color(Color::Muted),
+                    ),
+                )
+            })
     }
 }
 
@@ -969,7 +1042,11 @@ impl Item for TerminalView {
                     }
                 }
             },
-            None => (IconName::Terminal, Color::Muted, None),
+            None => match terminal.exit_status() {
+                Some(0) => (IconName::Check, Color::Muted, None),
+                Some(_) => (IconName::XCircle, Color::Error, None),
+                None => (IconName::Terminal, Color::Muted, None),
+            },
         };
 
         h_flex()
@@ -1152,8 +1229,8 @@ impl SearchableItem for TerminalView {
 
     fn supported_options() -> SearchOptions {
         SearchOptions {
-            case: false,
-            word: false,
+            case: true,
+            word: true,
             regex: true,
             replacement: false,
             selection: false,
@@ -1162,13 +1239,14 @@ impl SearchableItem for TerminalView {
 
     /// Clear stored matches
     fn clear_matches(&mut self, cx: &mut ViewContext<Self>) {
-        self.terminal().update(cx, |term, _| term.matches.clear())
+        self.terminal()
+            .update(cx, |term, cx| term.clear_matches(cx))
     }
 
     /// Store matches returned from find_matches somewhere for rendering
     fn update_matches(&mut self, matches: &[Self::Match], cx: &mut ViewContext<Self>) {
         self.terminal()
-            .update(cx, |term, _| term.matches = matches.to_vec())
+            .update(cx, |term, cx| term.set_matches(matches.to_vec(), cx))
     }
 
     /// Returns the selection content to pre-load into this search
@@ -1216,11 +1294,23 @@ impl SearchableItem for TerminalView {
             SearchQuery::Regex { .. } => regex_search_for_query(&query),
         };
 
-        if let Some(s) = searcher {
-            self.terminal()
-                .update(cx, |term, cx| term.find_matches(s, cx))
+        let Some(searcher) = searcher else {
+            return Task::ready(vec![]);
+        };
+        // Whole word queries skip refining the previous matches, since extending one can
+        // match where the shorter one didn't: "foo" misses "foobar", but "foobar" doesn't
+        if matches!(&*query, SearchQuery::Text { .. }) && !query.whole_word() {
+            let pattern = search_pattern(
+                &regex_to_literal(query.as_str()),
+                false,
+                query.case_sensitive(),
+            );
+            self.terminal().update(cx, |term, cx| {
+                term.find_literal_matches(pattern, searcher, cx)
+            })
         } else {
-            Task::ready(vec![])
+            self.terminal()
+                .update(cx, |term, cx| term.find_matches(searcher, cx))
         }
     }
 
@@ -1299,6 +1389,90 @@ mod tests {
     use std::path::Path;
     use workspace::AppState;
 
+    // Cells keep alacritty colors, which are resolved against the theme on every paint
+    #[test]
+    fn cell_colors_follow_theme() {
+        use crate::terminal_element::convert_color;
+        use terminal::alacritty_terminal::vte::ansi::{Color, NamedColor};
+
+        let family = theme::zed_pro_family();
+        let (light, dark) = (&family.themes[0], &family.themes[1]);
+        assert_ne!(
+            light.colors().terminal_ansi_red,
+            dark.colors().terminal_ansi_red
+        );
+
+        for color in [Color::Named(NamedColor::Red), Color::Indexed(1)] {
+            assert_eq!(
+                convert_color(&color, light),
+                light.colors().terminal_ansi_red
+            );
+            assert_eq!(convert_color(&color, dark), dark.colors().terminal_ansi_red);
+        }
+        assert_eq!(
+            convert_color(&Color::Named(NamedColor::Foreground), dark),
+            dark.colors().terminal_foreground
+        );
+    }
+
+    #[test]
+    fn minimum_contrast_adjusts_unreadable_colors() {
+        use crate::terminal_element::ensure_minimum_contrast;
+        use gpui::hsla;
+
+        let dark_gray = hsla(0., 0., 0.2, 1.);
+        let black = hsla(0., 0., 0., 1.);
+        let white = hsla(0., 0., 1., 1.);
+
+        // Readable colors are left alone
+        assert_eq!(ensure_minimum_contrast(white, black, 4.5), white);
+        assert_eq!(ensure_minimum_contrast(dark_gray, black, 1.), dark_gray);
+
+        // Dark on dark is lightened, just enough
+        let adjusted = ensure_minimum_contrast(dark_gray, black, 4.5);
+        assert!(adjusted.l > dark_gray.l && adjusted.l < 1.);
+        assert_eq!(adjusted.a, 1.);
+        assert!(ensure_minimum_contrast(dark_gray, black, 4.4).l < adjusted.l);
+
+        // Light on light is darkened
+        let light_gray = hsla(0., 0., 0.9, 1.);
+        assert!(ensure_minimum_contrast(light_gray, white, 4.5).l < light_gray.l);
+
+        // Unreachable ratios go as far as possible
+        assert_eq!(ensure_minimum_contrast(dark_gray, black, 30.), white);
+    }
+
+    #[test]
+    fn dim_text_uses_dim_factor() {
+        use crate::terminal_element::cell_foreground;
+        use terminal::alacritty_terminal::{
+            event::VoidListener,
+            index::{Column, Line, Point as AlacPoint},
+            term::Config,
+            vte::ansi::Processor,
+            Term,
+        };
+        use terminal::TerminalSize;
+
+        let mut term = Term::new(Config::default(), &TerminalSize::default(), VoidListener);
+        let mut parser: Processor = Processor::new();
+        for byte in b"\x1b[2mfaint\x1b[0m normal" {
+            parser.advance(&mut term, *byte);
+        }
+
+        let theme = &theme::zed_pro_family().themes[1];
+        let foreground = |column| {
+            let cell = &term.grid()[AlacPoint::new(Line(0), Column(column))];
+            cell_foreground(cell.flags, cell.fg, cell.bg, 1., 0.5, theme)
+        };
+
+        let normal = foreground(6);
+        assert_eq!(normal, theme.colors().terminal_foreground);
+        let faint = foreground(0);
+        assert_eq!(faint.a, normal.a * 0.5);
+        assert_eq!((faint.h, faint.s, faint.l), (normal.h, normal.s, normal.l));
+    }
+
     // Working directory calculation tests
 
     // No Worktrees in project -> home_dir()
@@ -1424,6 +1598,41 @@ mod tests {
         (project, workspace)
     }
 
+    /// Creates a terminal whose program exits right away without output, for tests
+    /// that don't depend on the terminal's content.
+    async fn exited_terminal(cx: &mut TestAppContext) -> Model<Terminal> {
+        use terminal::{TerminalBuilder, TerminalOptions};
+
+        cx.executor().allow_parking();
+        let window = cx.add_window(|_| gpui::EmptyView);
+        let (completion_tx, completion_rx) = smol::channel::unbounded();
+        let shell = if cfg!(windows) {
+            task::Shell::WithArguments {
+                program: "cmd".to_string(),
+                args: vec!["/C".to_string(), "exit".to_string()],
+            }
+        } else {
+            task::Shell::Program("true".to_string())
+        };
+        let terminal = cx.new_model(|cx| {
+            let options = TerminalOptions::from(TerminalSettings::get_global(cx));
+            TerminalBuilder::new(
+                None,
+                None,
+                shell,
+                Default::default(),
+                options,
+                window.into(),
+                completion_tx,
+                cx,
+            )
+            .unwrap()
+            .subscribe(cx)
+        });
+        completion_rx.recv().await.unwrap();
+        terminal
+    }
+
     /// Creates a worktree with 1 folder: /root{suffix}/
     async fn create_folder_wt(
         project: Model<Project>,
@@ -1480,6 +1689,58 @@ mod tests {
         });
     }
 
+    #[gpui::test]
+    async fn new_matches_reset_the_selected_match(cx: &mut TestAppContext) {
+        use terminal::alacritty_terminal::index::{Column, Line};
+
+        init_test(cx).await;
+        let terminal = exited_terminal(cx).await;
+        terminal.update(cx, |terminal, cx| {
+            let word =
+                |start, end| Point::new(Line(0), Column(start))..=Point::new(Line(0), Column(end));
+            terminal.set_matches(vec![word(0, 2), word(4, 6), word(8, 12)], cx);
+            terminal.activate_next_match();
+            terminal.activate_next_match();
+            assert_eq!(terminal.selected_match(), Some(1));
+
+            // Navigating the results of a new search starts over
+            terminal.set_matches(vec![word(0, 1), word(4, 5), word(8, 9)], cx);
+            assert_eq!(terminal.selected_match(), None);
+            terminal.activate_next_match();
+            assert_eq!(terminal.selected_match(), Some(0));
+        });
+    }
+
+    #[gpui::test]
+    async fn read_only_terminal_ignores_input(cx: &mut TestAppContext) {
+        init_test(cx).await;
+        let terminal = exited_terminal(cx).await;
+        let has_selection = |terminal: &Model<Terminal>, cx: &mut TestAppContext| {
+            terminal.update(cx, |terminal, cx| {
+                terminal.sync(cx);
+                terminal.last_content().selection.is_some()
+            })
+        };
+        terminal.update(cx, |terminal, _| terminal.select_all());
+        assert!(has_selection(&terminal, cx));
+
+        // Input clears the selection, unless it's ignored
+        terminal.update(cx, |terminal, _| {
+            terminal.set_read_only(true);
+            terminal.input("x".to_string());
+            terminal.input_bytes(b"x".to_vec());
+            assert!(!terminal.try_keystroke(&Keystroke::parse("x").unwrap(), false));
+            assert!(!terminal.try_keystroke(&Keystroke::parse("enter").unwrap(), false));
+        });
+        assert!(has_selection(&terminal, cx));
+
+        terminal.update(cx, |terminal, _| {
+            terminal.set_read_only(false);
+            assert!(terminal.try_keystroke(&Keystroke::parse("enter").unwrap(), false));
+        });
+        assert!(!has_selection(&terminal, cx));
+    }
+
     #[test]
     fn escapes_only_special_characters() {
         assert_eq!(regex_to_literal(r"test(\w)"), r"test\(\\w\)".to_string());
@@ -1489,4 +1750,18 @@ mod tests {
     fn empty_string_stays_empty() {
         assert_eq!(regex_to_literal(""), "".to_string());
     }
+
+    #[test]
+    fn search_pattern_applies_options() {
+        assert_eq!(search_pattern("foo", false, false), "(?i)foo");
+        assert_eq!(search_pattern("foo", false, true), "(?-i)foo");
+        assert_eq!(
+            search_pattern("foo|bar", true, false),
+            r"(?i)(?-u:\b)(?:foo|bar)(?-u:\b)"
+        );
+        assert_eq!(
+            search_pattern("foo|bar", true, true),
+            r"(?-i)(?-u:\b)(?:foo|bar)(?-u:\b)"
+        );
+    }
 }